@@ -0,0 +1,2 @@
+DROP FUNCTION entry_access_for_user;
+DROP INDEX contest_entries;
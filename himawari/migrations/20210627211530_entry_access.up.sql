@@ -0,0 +1,13 @@
+CREATE INDEX contest_entries ON entries (contest, id);
+
+CREATE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := contest_access_for_user(username, (SELECT contest FROM entries E WHERE E.id = entry));
+END;
+$$;
@@ -0,0 +1,3 @@
+DROP TABLE scores;
+DROP TABLE rubric_criteria;
+ALTER TABLE contests DROP COLUMN judging_closed;
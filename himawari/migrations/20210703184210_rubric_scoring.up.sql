@@ -0,0 +1,24 @@
+ALTER TABLE contests ADD COLUMN judging_closed BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE rubric_criteria (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    name VARCHAR(1024) NOT NULL CHECK (char_length(name) > 0),
+    description VARCHAR(65535),
+    min_score DOUBLE PRECISION NOT NULL DEFAULT 0,
+    max_score DOUBLE PRECISION NOT NULL DEFAULT 10,
+    weight DOUBLE PRECISION NOT NULL DEFAULT 1 CHECK (weight >= 0),
+    CHECK (min_score < max_score),
+    UNIQUE (contest, name)
+);
+
+CREATE TABLE scores (
+    entry INT8 NOT NULL REFERENCES entries ON DELETE CASCADE,
+    criterion INT8 NOT NULL REFERENCES rubric_criteria ON DELETE CASCADE,
+    judge VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    score DOUBLE PRECISION NOT NULL,
+    updated TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (entry, criterion, judge)
+);
+
+CREATE INDEX judge_scores ON scores (judge, entry);
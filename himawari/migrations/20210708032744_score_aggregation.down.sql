@@ -0,0 +1,2 @@
+ALTER TABLE contests DROP COLUMN aggregation, DROP COLUMN trim_fraction;
+DROP TYPE aggregation_method;
@@ -0,0 +1,5 @@
+CREATE TYPE aggregation_method AS ENUM ('mean', 'median', 'trimmed_mean', 'z_score');
+
+ALTER TABLE contests
+    ADD COLUMN aggregation aggregation_method NOT NULL DEFAULT 'mean',
+    ADD COLUMN trim_fraction DOUBLE PRECISION NOT NULL DEFAULT 0.1 CHECK (trim_fraction >= 0 AND trim_fraction < 0.5);
@@ -0,0 +1,4 @@
+DROP TABLE ballot_rankings;
+ALTER TABLE contests DROP COLUMN judging_mode, DROP COLUMN ballot_method;
+DROP TYPE ballot_method;
+DROP TYPE judging_mode;
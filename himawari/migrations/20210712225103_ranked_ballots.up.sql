@@ -0,0 +1,15 @@
+CREATE TYPE judging_mode AS ENUM ('scores', 'ballots');
+CREATE TYPE ballot_method AS ENUM ('borda', 'instant_runoff', 'schulze');
+
+ALTER TABLE contests
+    ADD COLUMN judging_mode judging_mode NOT NULL DEFAULT 'scores',
+    ADD COLUMN ballot_method ballot_method NOT NULL DEFAULT 'borda';
+
+CREATE TABLE ballot_rankings (
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    judge VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    entry INT8 NOT NULL REFERENCES entries ON DELETE CASCADE,
+    position INT4 NOT NULL CHECK (position >= 0),
+    PRIMARY KEY (contest, judge, entry),
+    UNIQUE (contest, judge, position)
+);
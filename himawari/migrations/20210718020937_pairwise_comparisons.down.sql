@@ -0,0 +1,11 @@
+DROP TABLE comparisons;
+ALTER TABLE contests DROP COLUMN pairwise_method;
+DROP TYPE pairwise_method;
+
+UPDATE contests SET judging_mode = 'scores' WHERE judging_mode = 'pairwise';
+ALTER TYPE judging_mode RENAME TO judging_mode_old;
+CREATE TYPE judging_mode AS ENUM ('scores', 'ballots');
+ALTER TABLE contests ALTER COLUMN judging_mode DROP DEFAULT;
+ALTER TABLE contests ALTER COLUMN judging_mode TYPE judging_mode USING judging_mode::TEXT::judging_mode;
+ALTER TABLE contests ALTER COLUMN judging_mode SET DEFAULT 'scores';
+DROP TYPE judging_mode_old;
@@ -0,0 +1,18 @@
+ALTER TYPE judging_mode ADD VALUE 'pairwise';
+CREATE TYPE pairwise_method AS ENUM ('bradley_terry', 'elo');
+
+ALTER TABLE contests ADD COLUMN pairwise_method pairwise_method NOT NULL DEFAULT 'bradley_terry';
+
+CREATE TABLE comparisons (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    judge VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    winner INT8 NOT NULL REFERENCES entries ON DELETE CASCADE,
+    loser INT8 NOT NULL REFERENCES entries ON DELETE CASCADE,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (winner <> loser)
+);
+
+CREATE INDEX contest_comparisons ON comparisons (contest, id);
+-- Each judge compares a pair once, whichever way round; comparing it again replaces the result.
+CREATE UNIQUE INDEX judge_comparisons ON comparisons (contest, judge, LEAST(winner, loser), GREATEST(winner, loser));
@@ -0,0 +1,45 @@
+ALTER TABLE contests ADD COLUMN reviews_per_entry INT4 CHECK (reviews_per_entry > 0);
+
+CREATE TABLE assignments (
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    entry INT8 NOT NULL REFERENCES entries ON DELETE CASCADE,
+    judge VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    assigned TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (entry, judge)
+);
+
+CREATE INDEX judge_assignments ON assignments (contest, judge);
+
+-- Judge/entry pairs that must never be assigned to each other.
+CREATE FUNCTION assignment_exclusions(IN contest contests.id%TYPE)
+RETURNS TABLE (judge users.username%TYPE, entry entries.id%TYPE)
+RETURNS NULL ON NULL INPUT
+STABLE
+LANGUAGE SQL
+AS $$
+    SELECT J.judge, E.id FROM all_judges($1) J, entries E
+    WHERE E.contest = $1 AND lower(E.creator) = lower(J.judge)
+$$;
+
+-- When a contest uses assignments, collaborators only see the entries assigned to them.
+CREATE OR REPLACE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+DECLARE
+    entry_contest contests.id%TYPE;
+BEGIN
+    SELECT E.contest INTO entry_contest FROM entries E WHERE E.id = entry_access_for_user.entry;
+    role := contest_access_for_user(username, entry_contest);
+
+    IF role = 'collaborator'
+        AND (SELECT C.reviews_per_entry FROM contests C WHERE C.id = entry_contest) IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM assignments A
+                        WHERE A.entry = entry_access_for_user.entry AND A.judge = entry_access_for_user.username) THEN
+        role := 'none';
+    END IF;
+END;
+$$;
@@ -0,0 +1,34 @@
+CREATE OR REPLACE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+DECLARE
+    entry_contest contests.id%TYPE;
+BEGIN
+    SELECT E.contest INTO entry_contest FROM entries E WHERE E.id = entry_access_for_user.entry;
+    role := contest_access_for_user(username, entry_contest);
+
+    IF role = 'collaborator'
+        AND (SELECT C.reviews_per_entry FROM contests C WHERE C.id = entry_contest) IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM assignments A
+                        WHERE A.entry = entry_access_for_user.entry AND A.judge = entry_access_for_user.username) THEN
+        role := 'none';
+    END IF;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION assignment_exclusions(IN contest contests.id%TYPE)
+RETURNS TABLE (judge users.username%TYPE, entry entries.id%TYPE)
+RETURNS NULL ON NULL INPUT
+STABLE
+LANGUAGE SQL
+AS $$
+    SELECT J.judge, E.id FROM all_judges($1) J, entries E
+    WHERE E.contest = $1 AND lower(E.creator) = lower(J.judge)
+$$;
+
+DROP FUNCTION judge_conflicted;
+DROP TABLE conflicts;
@@ -0,0 +1,67 @@
+CREATE TABLE conflicts (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    judge VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    -- Exactly one of entry or creator is set: a conflict is with one entry, or with everything a creator entered.
+    entry INT8 REFERENCES entries ON DELETE CASCADE,
+    creator VARCHAR(1024) CHECK (char_length(creator) > 0),
+    reason VARCHAR(1024),
+    declared_by VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((entry IS NULL) <> (creator IS NULL))
+);
+
+CREATE UNIQUE INDEX conflicts_entry ON conflicts (contest, judge, entry) WHERE entry IS NOT NULL;
+CREATE UNIQUE INDEX conflicts_creator ON conflicts (contest, judge, lower(creator)) WHERE creator IS NOT NULL;
+
+-- A judge is conflicted with their own entries and with anything they (or the owner) declared a conflict against.
+CREATE FUNCTION judge_conflicted(IN username users.username%TYPE, IN entry entries.id%TYPE)
+RETURNS BOOLEAN
+RETURNS NULL ON NULL INPUT
+STABLE
+LANGUAGE SQL
+AS $$
+    SELECT EXISTS (
+        SELECT 1 FROM entries E
+        WHERE E.id = $2
+          AND (lower(E.creator) = lower($1)
+               OR EXISTS (SELECT 1 FROM conflicts CF
+                          WHERE CF.contest = E.contest AND CF.judge = $1
+                            AND (CF.entry = E.id OR lower(CF.creator) = lower(E.creator))))
+    )
+$$;
+
+CREATE OR REPLACE FUNCTION assignment_exclusions(IN contest contests.id%TYPE)
+RETURNS TABLE (judge users.username%TYPE, entry entries.id%TYPE)
+RETURNS NULL ON NULL INPUT
+STABLE
+LANGUAGE SQL
+AS $$
+    SELECT J.judge, E.id FROM all_judges($1) J, entries E
+    WHERE E.contest = $1 AND judge_conflicted(J.judge, E.id)
+$$;
+
+-- Conflicted collaborators can't see the entry at all. Owners keep access so they can still run the contest.
+CREATE OR REPLACE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+DECLARE
+    entry_contest contests.id%TYPE;
+BEGIN
+    SELECT E.contest INTO entry_contest FROM entries E WHERE E.id = entry_access_for_user.entry;
+    role := contest_access_for_user(username, entry_contest);
+
+    IF role = 'collaborator' AND judge_conflicted(username, entry_access_for_user.entry) THEN
+        role := 'none';
+    ELSIF role = 'collaborator'
+        AND (SELECT C.reviews_per_entry FROM contests C WHERE C.id = entry_contest) IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM assignments A
+                        WHERE A.entry = entry_access_for_user.entry AND A.judge = entry_access_for_user.username) THEN
+        role := 'none';
+    END IF;
+END;
+$$;
@@ -0,0 +1 @@
+ALTER TABLE contests DROP COLUMN blind_judging;
@@ -0,0 +1 @@
+ALTER TABLE contests ADD COLUMN blind_judging BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,8 @@
+DROP TABLE contest_phase_changes;
+
+ALTER TABLE contests ADD COLUMN judging_closed BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE contests SET judging_closed = phase > 'judging';
+
+ALTER TABLE contests DROP COLUMN phase, DROP COLUMN phase_changed;
+
+DROP TYPE contest_phase;
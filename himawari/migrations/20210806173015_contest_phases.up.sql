@@ -0,0 +1,22 @@
+CREATE TYPE contest_phase AS ENUM ('draft', 'submissions', 'judging', 'deliberation', 'published', 'archived');
+
+ALTER TABLE contests
+    ADD COLUMN phase contest_phase NOT NULL DEFAULT 'draft',
+    ADD COLUMN phase_changed TIMESTAMPTZ NOT NULL DEFAULT now();
+
+-- Contests from before phases were already being judged.
+UPDATE contests SET phase = CASE WHEN judging_closed THEN 'deliberation'::contest_phase ELSE 'judging'::contest_phase END;
+
+ALTER TABLE contests DROP COLUMN judging_closed;
+
+CREATE TABLE contest_phase_changes (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    from_phase contest_phase NOT NULL,
+    to_phase contest_phase NOT NULL,
+    -- NULL when the change was made automatically.
+    changed_by VARCHAR(128) REFERENCES users ON DELETE SET NULL,
+    changed TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX contest_phase_changes_contest ON contest_phase_changes (contest);
@@ -0,0 +1,7 @@
+DROP INDEX contests_judging_deadline;
+DROP INDEX contests_submission_deadline;
+
+ALTER TABLE contests
+    DROP CONSTRAINT deadlines_in_order,
+    DROP COLUMN judging_deadline,
+    DROP COLUMN submission_deadline;
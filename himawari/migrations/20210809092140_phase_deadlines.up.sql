@@ -0,0 +1,8 @@
+ALTER TABLE contests
+    ADD COLUMN submission_deadline TIMESTAMPTZ,
+    ADD COLUMN judging_deadline TIMESTAMPTZ,
+    ADD CONSTRAINT deadlines_in_order CHECK (submission_deadline < judging_deadline);
+
+-- Lets the scheduler find due contests without scanning everything.
+CREATE INDEX contests_submission_deadline ON contests (submission_deadline) WHERE phase = 'submissions';
+CREATE INDEX contests_judging_deadline ON contests (judging_deadline) WHERE phase = 'judging';
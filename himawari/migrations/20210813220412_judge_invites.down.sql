@@ -0,0 +1,3 @@
+DROP FUNCTION invite_status;
+DROP TABLE judge_invites;
+DROP TYPE invite_status;
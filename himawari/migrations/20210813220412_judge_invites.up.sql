@@ -0,0 +1,31 @@
+CREATE TYPE invite_status AS ENUM ('pending', 'accepted', 'revoked', 'expired');
+
+CREATE TABLE judge_invites (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    token_hash BYTEA NOT NULL UNIQUE,
+    -- Set if the invite was emailed rather than only shared as a link.
+    email CITEXT,
+    created_by VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires TIMESTAMPTZ NOT NULL,
+    revoked TIMESTAMPTZ,
+    accepted_by VARCHAR(128) REFERENCES users ON DELETE SET NULL,
+    accepted TIMESTAMPTZ,
+    CHECK (revoked IS NULL OR accepted IS NULL)
+);
+
+CREATE INDEX judge_invites_contest ON judge_invites (contest);
+
+CREATE FUNCTION invite_status(IN invite judge_invites)
+RETURNS invite_status
+STABLE
+LANGUAGE SQL
+AS $$
+    SELECT CASE
+        WHEN invite.accepted IS NOT NULL THEN 'accepted'::invite_status
+        WHEN invite.revoked IS NOT NULL THEN 'revoked'::invite_status
+        WHEN invite.expires <= now() THEN 'expired'::invite_status
+        ELSE 'pending'::invite_status
+    END
+$$;
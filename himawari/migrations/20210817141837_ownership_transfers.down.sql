@@ -0,0 +1,2 @@
+DROP TABLE ownership_transfers;
+DROP TYPE transfer_status;
@@ -0,0 +1,17 @@
+CREATE TYPE transfer_status AS ENUM ('pending', 'accepted', 'declined', 'cancelled');
+
+CREATE TABLE ownership_transfers (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    contest INT8 NOT NULL REFERENCES contests ON DELETE CASCADE,
+    from_owner VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    to_owner VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    status transfer_status NOT NULL DEFAULT 'pending',
+    proposed TIMESTAMPTZ NOT NULL DEFAULT now(),
+    resolved TIMESTAMPTZ,
+    CHECK (from_owner <> to_owner),
+    CHECK ((status = 'pending') = (resolved IS NULL))
+);
+
+-- A contest has at most one open proposal at a time.
+CREATE UNIQUE INDEX ownership_transfers_pending ON ownership_transfers (contest) WHERE status = 'pending';
+CREATE INDEX ownership_transfers_to_owner ON ownership_transfers (to_owner) WHERE status = 'pending';
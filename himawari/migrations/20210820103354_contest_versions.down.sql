@@ -0,0 +1,3 @@
+DROP TRIGGER contests_version ON contests;
+DROP FUNCTION bump_contest_version;
+ALTER TABLE contests DROP COLUMN version;
@@ -0,0 +1,15 @@
+ALTER TABLE contests ADD COLUMN version INT8 NOT NULL DEFAULT 1;
+
+-- Any change to a contest bumps its version, so clients holding an old copy can be told it's stale.
+CREATE FUNCTION bump_contest_version()
+RETURNS TRIGGER
+LANGUAGE plpgsql
+AS $$
+BEGIN
+    NEW.version := OLD.version + 1;
+    RETURN NEW;
+END;
+$$;
+
+CREATE TRIGGER contests_version BEFORE UPDATE ON contests
+    FOR EACH ROW EXECUTE FUNCTION bump_contest_version();
@@ -0,0 +1,31 @@
+CREATE OR REPLACE FUNCTION contest_access_for_user(IN username users.username%TYPE, IN contest contests.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := 'none';
+
+    IF (SELECT owner FROM contests C WHERE C.id = contest) = username THEN
+        role := 'owner';
+    ELSIF (username IN (SELECT all_judges(contest))) THEN
+        role := 'collaborator';
+    END IF;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION user_contests(IN username users.username%TYPE)
+RETURNS SETOF contests
+RETURNS NULL ON NULL INPUT
+STABLE
+LANGUAGE SQL
+AS $$
+    (SELECT * FROM contests WHERE owner = username)
+    UNION DISTINCT
+    (SELECT C.* FROM contests C, contest_judges CJ WHERE CJ.judge = username AND C.id = CJ.contest)
+$$;
+
+DELETE FROM contests WHERE deleted IS NOT NULL;
+ALTER TABLE contests DROP COLUMN deleted;
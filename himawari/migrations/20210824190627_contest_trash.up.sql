@@ -0,0 +1,37 @@
+ALTER TABLE contests ADD COLUMN deleted TIMESTAMPTZ;
+
+CREATE INDEX contests_deleted ON contests (deleted) WHERE deleted IS NOT NULL;
+
+CREATE OR REPLACE FUNCTION user_contests(IN username users.username%TYPE)
+RETURNS SETOF contests
+RETURNS NULL ON NULL INPUT
+STABLE
+LANGUAGE SQL
+AS $$
+    (SELECT * FROM contests WHERE owner = username AND deleted IS NULL)
+    UNION DISTINCT
+    (SELECT C.* FROM contests C, contest_judges CJ WHERE CJ.judge = username AND C.id = CJ.contest AND C.deleted IS NULL)
+$$;
+
+-- Nobody has access to a contest in the trash; the owner can only restore it.
+CREATE OR REPLACE FUNCTION contest_access_for_user(IN username users.username%TYPE, IN contest contests.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := 'none';
+
+    IF EXISTS (SELECT 1 FROM contests C WHERE C.id = contest AND C.deleted IS NOT NULL) THEN
+        RETURN;
+    END IF;
+
+    IF (SELECT owner FROM contests C WHERE C.id = contest) = username THEN
+        role := 'owner';
+    ELSIF (username IN (SELECT all_judges(contest))) THEN
+        role := 'collaborator';
+    END IF;
+END;
+$$;
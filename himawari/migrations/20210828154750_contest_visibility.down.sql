@@ -0,0 +1,103 @@
+CREATE OR REPLACE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+DECLARE
+    entry_contest contests.id%TYPE;
+BEGIN
+    SELECT E.contest INTO entry_contest FROM entries E WHERE E.id = entry_access_for_user.entry;
+    role := contest_access_for_user(username, entry_contest);
+
+    IF role = 'collaborator' AND judge_conflicted(username, entry_access_for_user.entry) THEN
+        role := 'none';
+    ELSIF role = 'collaborator'
+        AND (SELECT C.reviews_per_entry FROM contests C WHERE C.id = entry_contest) IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM assignments A
+                        WHERE A.entry = entry_access_for_user.entry AND A.judge = entry_access_for_user.username) THEN
+        role := 'none';
+    END IF;
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION contest_access_for_user(IN username users.username%TYPE, IN contest contests.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := 'none';
+
+    IF EXISTS (SELECT 1 FROM contests C WHERE C.id = contest AND C.deleted IS NOT NULL) THEN
+        RETURN;
+    END IF;
+
+    IF (SELECT owner FROM contests C WHERE C.id = contest) = username THEN
+        role := 'owner';
+    ELSIF (username IN (SELECT all_judges(contest))) THEN
+        role := 'collaborator';
+    END IF;
+END;
+$$;
+
+DROP FUNCTION entry_access_for_anonymous;
+DROP FUNCTION contest_access_for_anonymous;
+
+ALTER TABLE contests DROP COLUMN visibility;
+DROP TYPE contest_visibility;
+
+-- Postgres can't drop an enum value, so access_role is rebuilt without 'viewer'.
+ALTER TYPE access_role RENAME TO access_role_old;
+CREATE TYPE access_role AS ENUM ('none', 'collaborator', 'owner', 'moderator', 'admin');
+-- Functions returning the type must be recreated against the new one.
+DROP FUNCTION entry_access_for_user;
+DROP FUNCTION contest_access_for_user;
+CREATE FUNCTION contest_access_for_user(IN username users.username%TYPE, IN contest contests.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := 'none';
+
+    IF EXISTS (SELECT 1 FROM contests C WHERE C.id = contest AND C.deleted IS NOT NULL) THEN
+        RETURN;
+    END IF;
+
+    IF (SELECT owner FROM contests C WHERE C.id = contest) = username THEN
+        role := 'owner';
+    ELSIF (username IN (SELECT all_judges(contest))) THEN
+        role := 'collaborator';
+    END IF;
+END;
+$$;
+CREATE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+DECLARE
+    entry_contest contests.id%TYPE;
+BEGIN
+    SELECT E.contest INTO entry_contest FROM entries E WHERE E.id = entry_access_for_user.entry;
+    role := contest_access_for_user(username, entry_contest);
+
+    IF role = 'collaborator' AND judge_conflicted(username, entry_access_for_user.entry) THEN
+        role := 'none';
+    ELSIF role = 'collaborator'
+        AND (SELECT C.reviews_per_entry FROM contests C WHERE C.id = entry_contest) IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM assignments A
+                        WHERE A.entry = entry_access_for_user.entry AND A.judge = entry_access_for_user.username) THEN
+        role := 'none';
+    END IF;
+END;
+$$;
+DROP TYPE access_role_old;
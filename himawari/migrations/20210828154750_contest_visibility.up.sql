@@ -0,0 +1,86 @@
+ALTER TYPE access_role ADD VALUE 'viewer' BEFORE 'collaborator';
+
+CREATE TYPE contest_visibility AS ENUM ('private', 'unlisted', 'public');
+
+ALTER TABLE contests ADD COLUMN visibility contest_visibility NOT NULL DEFAULT 'private';
+
+CREATE INDEX contests_public ON contests (id) WHERE visibility = 'public' AND deleted IS NULL;
+
+-- What someone who isn't logged in can do: look at public and unlisted contests, and nothing else.
+CREATE FUNCTION contest_access_for_anonymous(IN contest contests.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := 'none';
+
+    IF EXISTS (SELECT 1 FROM contests C
+               WHERE C.id = contest AND C.deleted IS NULL AND C.visibility <> 'private') THEN
+        role := 'viewer';
+    END IF;
+END;
+$$;
+
+CREATE FUNCTION entry_access_for_anonymous(IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := contest_access_for_anonymous((SELECT E.contest FROM entries E WHERE E.id = entry));
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION contest_access_for_user(IN username users.username%TYPE, IN contest contests.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+BEGIN
+    role := 'none';
+
+    IF EXISTS (SELECT 1 FROM contests C WHERE C.id = contest AND C.deleted IS NOT NULL) THEN
+        RETURN;
+    END IF;
+
+    IF (SELECT owner FROM contests C WHERE C.id = contest) = username THEN
+        role := 'owner';
+    ELSIF (username IN (SELECT all_judges(contest))) THEN
+        role := 'collaborator';
+    ELSE
+        role := contest_access_for_anonymous(contest);
+    END IF;
+END;
+$$;
+
+-- Judges who can't judge an entry can still see it as anyone else could.
+CREATE OR REPLACE FUNCTION entry_access_for_user(IN username users.username%TYPE, IN entry entries.id%TYPE, OUT role access_role)
+    RETURNS access_role
+    RETURNS NULL ON NULL INPUT
+    STABLE
+    LANGUAGE plpgsql
+AS
+$$
+DECLARE
+    entry_contest contests.id%TYPE;
+BEGIN
+    SELECT E.contest INTO entry_contest FROM entries E WHERE E.id = entry_access_for_user.entry;
+    role := contest_access_for_user(username, entry_contest);
+
+    IF role = 'collaborator' AND judge_conflicted(username, entry_access_for_user.entry) THEN
+        role := contest_access_for_anonymous(entry_contest);
+    ELSIF role = 'collaborator'
+        AND (SELECT C.reviews_per_entry FROM contests C WHERE C.id = entry_contest) IS NOT NULL
+        AND NOT EXISTS (SELECT 1 FROM assignments A
+                        WHERE A.entry = entry_access_for_user.entry AND A.judge = entry_access_for_user.username) THEN
+        role := contest_access_for_anonymous(entry_contest);
+    END IF;
+END;
+$$;
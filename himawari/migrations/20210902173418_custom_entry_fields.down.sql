@@ -0,0 +1,2 @@
+ALTER TABLE entries DROP COLUMN custom_fields;
+ALTER TABLE contests DROP COLUMN entry_schema;
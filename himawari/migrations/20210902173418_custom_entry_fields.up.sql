@@ -0,0 +1,2 @@
+ALTER TABLE contests ADD COLUMN entry_schema JSONB;
+ALTER TABLE entries ADD COLUMN custom_fields JSONB NOT NULL DEFAULT '{}';
@@ -0,0 +1,24 @@
+CREATE TYPE revocation_reason AS ENUM ('logout', 'reuse');
+
+-- Every login starts a family; each refresh replaces the family's token with a new one.
+CREATE TABLE token_families (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    username VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    revoked TIMESTAMPTZ,
+    revoked_reason revocation_reason,
+    CHECK ((revoked IS NULL) = (revoked_reason IS NULL))
+);
+
+CREATE INDEX token_families_username ON token_families (username);
+
+CREATE TABLE refresh_tokens (
+    token_hash BYTEA NOT NULL PRIMARY KEY,
+    family INT8 NOT NULL REFERENCES token_families ON DELETE CASCADE,
+    issued TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires TIMESTAMPTZ NOT NULL,
+    -- Set when the token is exchanged. Presenting it again revokes the family.
+    used TIMESTAMPTZ
+);
+
+CREATE INDEX refresh_tokens_family ON refresh_tokens (family);
@@ -0,0 +1,12 @@
+ALTER TABLE token_families
+    DROP COLUMN last_used,
+    DROP COLUMN user_agent,
+    DROP COLUMN ip;
+
+UPDATE token_families SET revoked_reason = 'logout' WHERE revoked_reason = 'remote';
+
+ALTER TYPE revocation_reason RENAME TO revocation_reason_old;
+CREATE TYPE revocation_reason AS ENUM ('logout', 'reuse');
+ALTER TABLE token_families
+    ALTER COLUMN revoked_reason TYPE revocation_reason USING revoked_reason::TEXT::revocation_reason;
+DROP TYPE revocation_reason_old;
@@ -0,0 +1,7 @@
+ALTER TYPE revocation_reason ADD VALUE 'remote';
+
+-- A token family is what users see as a session.
+ALTER TABLE token_families
+    ADD COLUMN last_used TIMESTAMPTZ NOT NULL DEFAULT now(),
+    ADD COLUMN user_agent VARCHAR(512),
+    ADD COLUMN ip VARCHAR(64);
@@ -0,0 +1,2 @@
+ALTER TABLE contests DROP COLUMN require_verified_email;
+DROP TABLE email_verification_sends;
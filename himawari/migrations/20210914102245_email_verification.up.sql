@@ -0,0 +1,10 @@
+-- Each verification email sent, kept so resends can be rate limited.
+CREATE TABLE email_verification_sends (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    username VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    sent TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX email_verification_sends_username ON email_verification_sends (username, sent);
+
+ALTER TABLE contests ADD COLUMN require_verified_email BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,9 @@
+DROP TABLE password_resets;
+
+UPDATE token_families SET revoked_reason = 'logout' WHERE revoked_reason = 'password_reset';
+
+ALTER TYPE revocation_reason RENAME TO revocation_reason_old;
+CREATE TYPE revocation_reason AS ENUM ('logout', 'reuse', 'remote');
+ALTER TABLE token_families
+    ALTER COLUMN revoked_reason TYPE revocation_reason USING revoked_reason::TEXT::revocation_reason;
+DROP TYPE revocation_reason_old;
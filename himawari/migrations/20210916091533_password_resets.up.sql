@@ -0,0 +1,12 @@
+ALTER TYPE revocation_reason ADD VALUE 'password_reset';
+
+CREATE TABLE password_resets (
+    id SERIAL8 NOT NULL PRIMARY KEY,
+    token_hash BYTEA NOT NULL UNIQUE,
+    username VARCHAR(128) NOT NULL REFERENCES users ON DELETE CASCADE,
+    created TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires TIMESTAMPTZ NOT NULL,
+    used TIMESTAMPTZ
+);
+
+CREATE INDEX password_resets_username ON password_resets (username);
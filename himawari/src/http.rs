@@ -1,8 +1,79 @@
 use once_cell::sync::Lazy;
 use reqwest::Client;
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Responder};
+use rocket::serde::json;
+use rocket::http::Status;
+use rocket::Request;
+use serde::{Deserialize, Serialize};
 
 static CLIENT: Lazy<Client> = Lazy::new(Client::new);
 
 pub fn client() -> Client {
     Client::clone(&CLIENT)
+}
+
+/// The version in the request's `If-Match` header, if it has one.
+#[derive(Debug, Clone, Copy)]
+pub struct IfMatch(pub Option<i64>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let header = match request.headers().get_one("If-Match") {
+            Some(h) => h,
+            None => return request::Outcome::Success(IfMatch(None)),
+        };
+
+        let tag = header.trim().trim_start_matches("W/").trim_matches('"');
+        match tag.parse::<i64>() {
+            Ok(v) => request::Outcome::Success(IfMatch(Some(v))),
+            Err(_) => request::Outcome::Failure((Status::BadRequest, ())),
+        }
+    }
+}
+
+/// A request body that can also say which version it's based on, for clients that can't send
+/// `If-Match`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Versioned<T> {
+    #[serde(flatten)]
+    pub body: T,
+    #[serde(default)]
+    pub version: Option<i64>,
+}
+
+/// What a request says about where it came from, recorded against the session it starts or uses.
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+/// Longest user agent kept; anything past this is cut off.
+const MAX_USER_AGENT: usize = 512;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientInfo {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let user_agent = request.headers().get_one("User-Agent")
+            .map(|ua| ua.chars().take(MAX_USER_AGENT).collect());
+        let ip = request.client_ip().map(|ip| ip.to_string());
+        request::Outcome::Success(ClientInfo { user_agent, ip })
+    }
+}
+
+/// A JSON response with an `ETag` header holding the version of what's in it.
+pub struct Tagged<T>(pub T, pub i64);
+
+impl<'r, 'o: 'r, T: Serialize> Responder<'r, 'o> for Tagged<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = json::Json(self.0).respond_to(request)?;
+        response.set_raw_header("ETag", format!("\"{}\"", self.1));
+        Ok(response)
+    }
 }
\ No newline at end of file
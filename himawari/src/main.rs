@@ -22,6 +22,9 @@ mod recaptcha;
 mod http;
 mod logging;
 mod secure;
+mod tally;
+mod scheduler;
+mod mail;
 
 #[tokio::main]
 async fn main() {
@@ -56,6 +59,13 @@ async fn main() {
         .parse::<u16>()
         .expect("Invalid port number.");
 
+    mail::check_config();
+
+    info!("starting background jobs");
+    tokio::spawn(scheduler::run());
+    tokio::spawn(scheduler::purge_trash());
+    tokio::spawn(scheduler::purge_tokens());
+
     info!("starting server");
     let config = Figment::from(Config::default())
         .merge(("port", port));
@@ -66,10 +76,79 @@ async fn main() {
                rocket::routes![
                    routes::auth::register,
                    routes::auth::login,
+                   routes::auth::refresh,
+                   routes::auth::logout,
+                   routes::verification::verify_email,
+                   routes::verification::resend_verification,
+                   routes::passwords::forgot_password,
+                   routes::passwords::reset_password,
+                   routes::profile::my_profile,
+                   routes::profile::update_profile,
+                   routes::profile::change_password,
+                   routes::profile::change_email,
+                   routes::sessions::my_sessions,
+                   routes::sessions::revoke_session,
+                   routes::sessions::revoke_other_sessions,
                    routes::contests::get_contest,
+                   routes::contests::update_contest,
                    routes::contests::delete_contest,
+                   routes::contests::trashed_contests,
+                   routes::contests::restore_contest,
+                   routes::archive::export_contest,
+                   routes::archive::import_contest,
                    routes::contests::my_contests,
-                   routes::contests::new_contest
+                   routes::contests::public_contests,
+                   routes::contests::new_contest,
+                   routes::contests::change_phase,
+                   routes::contests::phase_history,
+                   routes::contests::get_deadlines,
+                   routes::contests::set_deadlines,
+                   routes::contests::get_requirements,
+                   routes::contests::set_requirements,
+                   routes::judges::get_judges,
+                   routes::judges::add_judge,
+                   routes::judges::remove_judge,
+                   routes::invites::get_invites,
+                   routes::invites::new_invite,
+                   routes::invites::revoke_invite,
+                   routes::invites::accept_invite,
+                   routes::transfers::get_transfers,
+                   routes::transfers::propose_transfer,
+                   routes::transfers::cancel_transfer,
+                   routes::transfers::incoming_transfers,
+                   routes::transfers::accept_transfer,
+                   routes::transfers::decline_transfer,
+                   routes::entries::new_entry,
+                   routes::entries::get_entry,
+                   routes::entries::update_entry,
+                   routes::entries::delete_entry,
+                   routes::entries::contest_entries,
+                   routes::entries::get_entry_schema,
+                   routes::entries::set_entry_schema,
+                   routes::scores::get_rubric,
+                   routes::scores::new_criterion,
+                   routes::scores::update_criterion,
+                   routes::scores::delete_criterion,
+                   routes::scores::my_contest_scores,
+                   routes::scores::my_entry_scores,
+                   routes::scores::submit_scores,
+                   routes::results::get_scoring,
+                   routes::results::set_scoring,
+                   routes::results::get_results,
+                   routes::ballots::my_ballot,
+                   routes::ballots::submit_ballot,
+                   routes::ballots::withdraw_ballot,
+                   routes::comparisons::next_pair,
+                   routes::comparisons::my_comparisons,
+                   routes::comparisons::new_comparison,
+                   routes::assignments::get_assignments,
+                   routes::assignments::my_assignments,
+                   routes::assignments::configure_assignments,
+                   routes::assignments::rebalance_assignments,
+                   routes::assignments::disable_assignments,
+                   routes::conflicts::get_conflicts,
+                   routes::conflicts::declare_conflict,
+                   routes::conflicts::withdraw_conflict
                ]);
     #[cfg(debug_assertions)]
     let r = r.mount("/debug", rocket::routes![routes::debug::echo_token]);
@@ -0,0 +1,460 @@
+//! A self-contained copy of a contest, for moving it between instances or keeping it as a backup.
+//!
+//! Ids in an archive only matter within it: importing gives every contest, criterion and entry a
+//! new id and rewrites the references between them. Invites, conflicts of interest, transfers and
+//! phase history aren't archived, and assignments are rebalanced after an import rather than copied.
+//!
+//! Judgments that don't count toward results aren't exported, since the conflicts that rule them
+//! out aren't either.
+//!
+//! Only the importer's own judgments are imported. An archive is just a file anyone could have
+//! written, so it can't be trusted to say what other users judged, or to make them judges.
+
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::{User, Username};
+use crate::model::verification;
+use crate::model::contests::{Contest, Deadlines, Phase, Requirements, Visibility, JudgingMode};
+use crate::model::entries::{Entry, EntryFields, EntrySchema};
+use crate::model::scores::{Criterion, CriterionFields, Score};
+use crate::model::ballots::Ballot;
+use crate::model::comparisons::Comparison;
+use crate::model::conflicts::Conflict;
+use crate::model::results::ScoringSettings;
+use crate::model::assignments::Assignment;
+use crate::model::transfers::Transfer;
+use crate::tally::aggregate::Method;
+use crate::tally::{ranked, pairwise};
+use crate::{db, api, secure};
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use validator::Validate;
+
+/// The archive format this build writes. Archives from other versions are refused.
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("Archive format version {0} isn't supported; this server reads version {}.", FORMAT_VERSION)]
+    UnsupportedVersion(u32),
+    #[error("The archive refers to entry {0}, which it doesn't contain.")]
+    UnknownEntry(ItemId),
+    #[error("The archive refers to criterion {0}, which it doesn't contain.")]
+    UnknownCriterion(ItemId),
+    #[error("{0} doesn't have an account on this server.")]
+    UnknownOwner(Username),
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::UnsupportedVersion(_) |
+            Error::UnknownEntry(_) |
+            Error::UnknownCriterion(_) |
+            Error::UnknownOwner(_) => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+/// A contest's own settings, as archived.
+#[derive(Clone, Debug, Serialize, Deserialize, Validate)]
+pub struct ContestMeta {
+    #[validate(length(min = 1, max = 1024), non_control_character)]
+    pub name: String,
+    /// Who owned the contest when it was exported.
+    pub owner: Username,
+    pub visibility: Visibility,
+    pub phase: Phase,
+    #[validate]
+    pub scoring: ScoringSettings,
+    #[validate]
+    pub deadlines: Deadlines,
+    #[serde(rename = "reviewsPerEntry", default)]
+    pub reviews_per_entry: Option<i32>,
+    #[serde(rename = "entrySchema", default)]
+    pub entry_schema: EntrySchema,
+    #[serde(default)]
+    pub requirements: Requirements,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Archive {
+    pub version: u32,
+    pub exported: chrono::DateTime<Utc>,
+    pub contest: ContestMeta,
+    /// Judges other than the owner.
+    #[serde(default)]
+    pub judges: Vec<Username>,
+    #[serde(default)]
+    pub rubric: Vec<Criterion>,
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+    #[serde(default)]
+    pub scores: Vec<Score>,
+    #[serde(default)]
+    pub ballots: Vec<Ballot>,
+    #[serde(default)]
+    pub comparisons: Vec<Comparison>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ImportRequest {
+    /// Who should own the imported contest. If it isn't the importer, the contest is created for
+    /// the importer and offered to this user as an ownership transfer.
+    #[serde(default)]
+    pub owner: Option<Username>,
+    pub archive: Archive,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ImportReport {
+    pub contest: Contest,
+    /// Judges of the archived contest who have an account here, other than the importer. They
+    /// aren't made judges again, since that would sign them up without an invite and skip any
+    /// verified email requirement, and their judgments can't be vouched for by the importer. They
+    /// can be invited to judge again.
+    #[serde(rename = "excludedJudges")]
+    pub excluded_judges: Vec<Username>,
+    /// Judges of the archived contest who don't have an account here. Their judgments were left
+    /// out too.
+    #[serde(rename = "unknownUsers")]
+    pub unknown_users: Vec<Username>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transfer: Option<Transfer>,
+}
+
+impl Archive {
+    /// Bundles up everything about a contest that can be carried to another instance, hiding
+    /// entrants from `role` if the contest is judged blind.
+    pub async fn export(contest: &Contest, role: &secure::Role) -> api::Result<Self> {
+        let scoring = ScoringSettings::load(contest.id).await?;
+        let reviews_per_entry = sqlx::query_scalar!(
+            r#"
+            SELECT reviews_per_entry FROM contests WHERE id = $1;
+            "#,
+            *contest.id
+        ).fetch_one(db::pool())
+            .await?;
+
+        let mut entries: Vec<Entry> = sqlx::query_as!(
+            Entry,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", name, creator, url, description, custom_fields
+            FROM entries
+            WHERE contest = $1
+            ORDER BY id;
+            "#,
+            *contest.id
+        ).fetch_all(db::pool())
+            .await?;
+        if Entry::identity_hidden_from(contest.id, role).await? {
+            let schema = EntrySchema::load(contest.id).await?;
+            entries = entries.into_iter().map(|e| e.blinded(&schema)).collect();
+        }
+
+        // Leave out the same judgments results do.
+        let judges = contest.judges().await?;
+        let excluded = Conflict::excluded_pairs(contest.id).await?;
+        let counts = |entry: &ItemId, judge: &Username| !excluded.contains(&(*entry, judge.clone()));
+
+        let scores = Score::all_for_contest(contest.id).await?
+            .into_iter()
+            .filter(|s| judges.contains(&s.judge) && counts(&s.entry, &s.judge))
+            .collect();
+        let ballots = Ballot::all_for_contest(contest.id).await?
+            .into_iter()
+            .filter(|b| judges.contains(&b.judge))
+            .map(|b| Ballot {
+                ranking: b.ranking.iter().copied().filter(|e| counts(e, &b.judge)).collect(),
+                ..b
+            })
+            .collect();
+        let comparisons = Comparison::all_for_contest(contest.id).await?
+            .into_iter()
+            .filter(|c| judges.contains(&c.judge) && counts(&c.winner, &c.judge) && counts(&c.loser, &c.judge))
+            .collect();
+
+        Ok(Self {
+            version: FORMAT_VERSION,
+            exported: Utc::now(),
+            contest: ContestMeta {
+                name: contest.name.clone(),
+                owner: contest.owner.clone(),
+                visibility: contest.visibility,
+                phase: contest.phase,
+                scoring,
+                deadlines: Contest::deadlines(contest.id).await?,
+                reviews_per_entry,
+                entry_schema: EntrySchema::load(contest.id).await?,
+                requirements: Contest::requirements(contest.id).await?,
+            },
+            judges: contest.invited_judges().await?.into_iter().map(|j| j.username).collect(),
+            rubric: Criterion::for_contest(contest.id).await?,
+            entries,
+            scores,
+            ballots,
+            comparisons,
+        })
+    }
+
+    /// Checks everything a user could have typed in, and that every reference points at
+    /// something in the archive, the same way it would have been checked when it was submitted.
+    fn check(&self) -> api::Result<()> {
+        if self.version != FORMAT_VERSION {
+            return Err(Error::UnsupportedVersion(self.version).into());
+        }
+
+        self.contest.validate()?;
+        self.contest.entry_schema.ensure_valid()?;
+
+        for c in &self.rubric {
+            CriterionFields {
+                name: c.name.clone(),
+                description: c.description.clone(),
+                min_score: c.min_score,
+                max_score: c.max_score,
+                weight: c.weight,
+            }.validate()?;
+        }
+        for e in &self.entries {
+            EntryFields {
+                name: e.name.clone(),
+                creator: e.creator.clone(),
+                url: e.url.clone(),
+                description: e.description.clone(),
+                custom_fields: e.custom_fields.clone(),
+            }.validate()?;
+            self.contest.entry_schema.check(&e.custom_fields)?;
+        }
+
+        let entries: BTreeSet<ItemId> = self.entries.iter().map(|e| e.id).collect();
+        let rubric: BTreeMap<ItemId, &Criterion> = self.rubric.iter().map(|c| (c.id, c)).collect();
+
+        for s in &self.scores {
+            if !entries.contains(&s.entry) {
+                return Err(Error::UnknownEntry(s.entry).into());
+            }
+            rubric.get(&s.criterion)
+                .ok_or(Error::UnknownCriterion(s.criterion))?
+                .check(s.score)?;
+        }
+        for b in &self.ballots {
+            Ballot::check_ranking(&entries, &b.ranking)?;
+        }
+        for c in &self.comparisons {
+            Comparison::check_pair(&entries, c.winner, c.loser)?;
+        }
+        Ok(())
+    }
+
+    /// Everyone the archive names as a judge, including the owner.
+    fn judges(&self) -> BTreeSet<&Username> {
+        std::iter::once(&self.contest.owner)
+            .chain(&self.judges)
+            .chain(self.scores.iter().map(|s| &s.judge))
+            .chain(self.ballots.iter().map(|b| &b.judge))
+            .chain(self.comparisons.iter().map(|c| &c.judge))
+            .collect()
+    }
+
+    /// Recreates the archived contest as a new contest owned by `importer`, then offers it to
+    /// `owner` if that's someone else.
+    pub async fn import(&self, importer: &Username, owner: Option<&Username>) -> api::Result<ImportReport> {
+        self.check()?;
+
+        if let Some(owner) = owner {
+            let known = sqlx::query_scalar!(
+                r#"
+                SELECT EXISTS (SELECT 1 FROM users WHERE username = $1) as "known!";
+                "#,
+                owner.as_str()
+            ).fetch_one(db::pool())
+                .await?;
+            if !known {
+                return Err(Error::UnknownOwner(owner.clone()).into());
+            }
+        }
+
+        let others: Vec<String> = self.judges().into_iter()
+            .filter(|j| *j != importer)
+            .map(|j| j.to_string())
+            .collect();
+        let known: BTreeSet<Username> = sqlx::query_scalar!(
+            r#"
+            SELECT username as "username: Username" FROM users WHERE username = ANY($1);
+            "#,
+            &others
+        ).fetch_all(db::pool())
+            .await?
+            .into_iter()
+            .collect();
+        let (excluded_judges, unknown_users): (Vec<Username>, Vec<Username>) = self.judges().into_iter()
+            .filter(|j| *j != importer)
+            .cloned()
+            .partition(|j| known.contains(j));
+
+        let meta = &self.contest;
+        let judged = self.scores.iter().any(|s| &s.judge == importer)
+            || self.ballots.iter().any(|b| &b.judge == importer)
+            || self.comparisons.iter().any(|c| &c.judge == importer);
+        if judged && meta.requirements.verified_email && !User::load_full(importer).await?.email_validated() {
+            return Err(verification::Error::Unverified(importer.clone()).into());
+        }
+
+        let mut tx = db::pool().begin().await?;
+
+        let contest = sqlx::query_as!(
+            Contest,
+            r#"
+            INSERT INTO contests (owner, name, visibility, phase, judging_mode, aggregation, trim_fraction, ballot_method,
+                                  pairwise_method, blind_judging, submission_deadline, judging_deadline,
+                                  reviews_per_entry, entry_schema, require_verified_email)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            RETURNING id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                      visibility as "visibility: _";
+            "#,
+            importer.as_str(),
+            meta.name,
+            meta.visibility as Visibility,
+            meta.phase as Phase,
+            meta.scoring.mode as JudgingMode,
+            meta.scoring.method as Method,
+            meta.scoring.trim_fraction,
+            meta.scoring.ballot_method as ranked::Method,
+            meta.scoring.pairwise_method as pairwise::Method,
+            meta.scoring.blind_judging,
+            meta.deadlines.submission_deadline,
+            meta.deadlines.judging_deadline,
+            meta.reviews_per_entry,
+            meta.entry_schema.schema,
+            meta.requirements.verified_email
+        ).fetch_one(&mut tx)
+            .await?;
+
+        let mut criteria: BTreeMap<ItemId, ItemId> = BTreeMap::new();
+        for c in &self.rubric {
+            let id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO rubric_criteria (contest, name, description, min_score, max_score, weight)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id as "id: ItemId";
+                "#,
+                *contest.id,
+                c.name,
+                c.description,
+                c.min_score,
+                c.max_score,
+                c.weight
+            ).fetch_one(&mut tx)
+                .await?;
+            criteria.insert(c.id, id);
+        }
+
+        let mut entries: BTreeMap<ItemId, ItemId> = BTreeMap::new();
+        for e in &self.entries {
+            let id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO entries (contest, name, creator, url, description, custom_fields)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id as "id: ItemId";
+                "#,
+                *contest.id,
+                e.name,
+                e.creator,
+                e.url,
+                e.description,
+                e.custom_fields
+            ).fetch_one(&mut tx)
+                .await?;
+            entries.insert(e.id, id);
+        }
+
+        // `check` has made sure every reference resolves.
+        let scores = self.scores.iter().filter(|s| &s.judge == importer);
+        let (mut score_entries, mut score_criteria, mut score_judges, mut values, mut updated) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for s in scores {
+            score_entries.push(*entries[&s.entry]);
+            score_criteria.push(*criteria[&s.criterion]);
+            score_judges.push(s.judge.to_string());
+            values.push(s.score);
+            updated.push(s.updated);
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO scores (entry, criterion, judge, score, updated)
+            SELECT * FROM UNNEST($1::INT8[], $2::INT8[], $3::VARCHAR[], $4::FLOAT8[], $5::TIMESTAMPTZ[]);
+            "#,
+            &score_entries,
+            &score_criteria,
+            &score_judges,
+            &values,
+            &updated
+        ).execute(&mut tx)
+            .await?;
+
+        for b in self.ballots.iter().filter(|b| &b.judge == importer) {
+            let ranking: Vec<i64> = b.ranking.iter().map(|e| *entries[e]).collect();
+            sqlx::query!(
+                r#"
+                INSERT INTO ballot_rankings (contest, judge, entry, position)
+                SELECT $1, $2, R.entry, (R.position - 1)::INT4
+                FROM UNNEST($3::INT8[]) WITH ORDINALITY AS R(entry, position);
+                "#,
+                *contest.id,
+                b.judge.as_str(),
+                &ranking
+            ).execute(&mut tx)
+                .await?;
+        }
+
+        let comparisons = self.comparisons.iter().filter(|c| &c.judge == importer);
+        let (mut comparison_judges, mut winners, mut losers, mut created) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for c in comparisons {
+            comparison_judges.push(c.judge.to_string());
+            winners.push(*entries[&c.winner]);
+            losers.push(*entries[&c.loser]);
+            created.push(c.created);
+        }
+        sqlx::query!(
+            r#"
+            INSERT INTO comparisons (contest, judge, winner, loser, created)
+            SELECT $1, C.judge, C.winner, C.loser, C.created
+            FROM UNNEST($2::VARCHAR[], $3::INT8[], $4::INT8[], $5::TIMESTAMPTZ[]) AS C(judge, winner, loser, created)
+            ORDER BY C.created;
+            "#,
+            *contest.id,
+            &comparison_judges,
+            &winners,
+            &losers,
+            &created
+        ).execute(&mut tx)
+            .await?;
+
+        if meta.reviews_per_entry.is_some() {
+            Assignment::rebalance_in(contest.id, &mut tx).await?;
+        }
+
+        let transfer = match owner {
+            Some(owner) if owner != importer => Some(Transfer::propose_in(&contest, owner, &mut tx).await?),
+            _ => None,
+        };
+
+        tx.commit().await?;
+        info!(contest = %contest.id, importer = %importer, excluded = excluded_judges.len(),
+              unknown = unknown_users.len(), "imported contest");
+
+        Ok(ImportReport {
+            contest,
+            excluded_judges,
+            unknown_users,
+            transfer,
+        })
+    }
+}
\ No newline at end of file
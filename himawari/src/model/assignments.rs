@@ -0,0 +1,383 @@
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::Contest;
+use crate::{db, api};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("This contest doesn't use judge assignments.")]
+    NotEnabled,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::NotEnabled => Status::Conflict,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Assignment {
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    #[serde(rename = "entryId")]
+    pub entry: ItemId,
+    pub judge: Username,
+    pub assigned: chrono::DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Debug, validator::Validate)]
+pub struct AssignmentSettings {
+    #[serde(rename = "reviewsPerEntry")]
+    #[validate(range(min = 1, max = 1000))]
+    pub reviews_per_entry: i32,
+}
+
+/// What a rebalance changed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Summary {
+    #[serde(rename = "reviewsPerEntry")]
+    pub reviews_per_entry: i32,
+    pub added: usize,
+    pub removed: usize,
+    /// Entries with fewer eligible judges than `reviewsPerEntry`.
+    pub understaffed: Vec<ItemId>,
+    /// Number of entries assigned to each judge.
+    pub load: BTreeMap<Username, usize>,
+}
+
+type Pair = (ItemId, Username);
+
+impl Assignment {
+    pub async fn all_for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Assignment,
+            r#"
+            SELECT contest as "contest: _", entry as "entry: _", judge as "judge: _", assigned
+            FROM assignments
+            WHERE contest = $1
+            ORDER BY entry, judge;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn for_judge(contest: ItemId, judge: &Username) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Assignment,
+            r#"
+            SELECT contest as "contest: _", entry as "entry: _", judge as "judge: _", assigned
+            FROM assignments
+            WHERE contest = $1 AND judge = $2
+            ORDER BY entry;
+            "#,
+            *contest,
+            judge.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn enabled(contest: ItemId) -> db::Result<bool> {
+        let reviews_per_entry = sqlx::query_scalar!(
+            r#"
+            SELECT reviews_per_entry FROM contests WHERE id = $1;
+            "#,
+            *contest
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(reviews_per_entry.is_some())
+    }
+
+    /// Turns assignments on for the contest with the given target and rebalances, as long as the
+    /// contest is still at `version`. Returns the new version along with what the rebalance did, or
+    /// `None` if someone else changed the contest first.
+    pub async fn configure(contest: &Contest, settings: &AssignmentSettings, version: i64) -> api::Result<Option<(i64, Summary)>> {
+        let updated = sqlx::query_scalar!(
+            r#"
+            UPDATE contests SET reviews_per_entry = $2 WHERE id = $1 AND version = $3
+            RETURNING version;
+            "#,
+            *contest.id,
+            settings.reviews_per_entry,
+            version
+        ).fetch_optional(db::pool())
+            .await?;
+
+        match updated {
+            Some(version) => Ok(Some((version, Self::rebalance(contest).await?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Turns assignments off, so every judge can see every entry again.
+    pub async fn disable(contest: ItemId) -> db::Result<()> {
+        let mut tx = db::pool().begin().await?;
+        sqlx::query!(
+            r#"
+            UPDATE contests SET reviews_per_entry = NULL WHERE id = $1;
+            "#,
+            *contest
+        ).execute(&mut tx)
+            .await?;
+        sqlx::query!(
+            r#"
+            DELETE FROM assignments WHERE contest = $1;
+            "#,
+            *contest
+        ).execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Brings the contest's assignments in line with its current judges, entries and exclusions.
+    /// Existing assignments are kept where possible, and never taken away from a judge who has
+    /// already scored, ranked or compared the entry.
+    pub async fn rebalance(contest: &Contest) -> api::Result<Summary> {
+        let mut tx = db::pool().begin().await?;
+        let out = Self::rebalance_in(contest.id, &mut tx).await?;
+        tx.commit().await?;
+        Ok(out)
+    }
+
+    /// Rebalances the contest's assignments as part of a larger change.
+    pub async fn rebalance_in(contest: ItemId, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> api::Result<Summary> {
+        // Serializes rebalances of the same contest, and holds off new judges and entries until
+        // it's done, so everything below is read as of the same moment.
+        let current = sqlx::query!(
+            r#"
+            SELECT owner as "owner: Username", reviews_per_entry FROM contests WHERE id = $1 FOR UPDATE;
+            "#,
+            *contest
+        ).fetch_one(&mut *tx)
+            .await?;
+        let reviews_per_entry = current.reviews_per_entry.ok_or(Error::NotEnabled)?;
+
+        let mut judges: BTreeSet<Username> = sqlx::query_scalar!(
+            r#"
+            SELECT judge as "judge: Username" FROM contest_judges WHERE contest = $1;
+            "#,
+            *contest
+        ).fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+        judges.insert(current.owner);
+
+        let entries: BTreeSet<ItemId> = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id: ItemId" FROM entries WHERE contest = $1;
+            "#,
+            *contest
+        ).fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect();
+
+        let existing: BTreeSet<Pair> = sqlx::query!(
+            r#"
+            SELECT entry as "entry: ItemId", judge as "judge: Username" FROM assignments WHERE contest = $1;
+            "#,
+            *contest
+        ).fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|r| (r.entry, r.judge))
+            .collect();
+
+        let excluded: BTreeSet<Pair> = sqlx::query!(
+            r#"
+            SELECT entry as "entry!: ItemId", judge as "judge!: Username" FROM assignment_exclusions($1);
+            "#,
+            *contest
+        ).fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|r| (r.entry, r.judge))
+            .collect();
+
+        // Whatever the judging mode, a judge who has already judged an entry keeps it.
+        let locked: BTreeSet<Pair> = sqlx::query!(
+            r#"
+            SELECT S.entry as "entry!: ItemId", S.judge as "judge!: Username"
+            FROM scores S JOIN entries E ON E.id = S.entry
+            WHERE E.contest = $1
+            UNION
+            SELECT entry, judge FROM ballot_rankings WHERE contest = $1
+            UNION
+            SELECT winner, judge FROM comparisons WHERE contest = $1
+            UNION
+            SELECT loser, judge FROM comparisons WHERE contest = $1;
+            "#,
+            *contest
+        ).fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .map(|r| (r.entry, r.judge))
+            .collect();
+
+        let target = reviews_per_entry as usize;
+        let balanced = balance(&judges, &entries, target, &existing, &excluded, &locked, &mut rand::thread_rng());
+
+        let removed: Vec<&Pair> = existing.difference(&balanced).collect();
+        let added: Vec<&Pair> = balanced.difference(&existing).collect();
+
+        let (entry_ids, judge_names): (Vec<i64>, Vec<String>) = removed.iter()
+            .map(|(e, j)| (**e, j.to_string()))
+            .unzip();
+        sqlx::query!(
+            r#"
+            DELETE FROM assignments A
+            USING UNNEST($2::INT8[], $3::VARCHAR[]) AS R(entry, judge)
+            WHERE A.contest = $1 AND A.entry = R.entry AND A.judge = R.judge;
+            "#,
+            *contest,
+            &entry_ids,
+            &judge_names
+        ).execute(&mut *tx)
+            .await?;
+
+        let (entry_ids, judge_names): (Vec<i64>, Vec<String>) = added.iter()
+            .map(|(e, j)| (**e, j.to_string()))
+            .unzip();
+        sqlx::query!(
+            r#"
+            INSERT INTO assignments (contest, entry, judge)
+            SELECT $1, R.entry, R.judge FROM UNNEST($2::INT8[], $3::VARCHAR[]) AS R(entry, judge);
+            "#,
+            *contest,
+            &entry_ids,
+            &judge_names
+        ).execute(&mut *tx)
+            .await?;
+
+        let mut per_entry: BTreeMap<ItemId, usize> = entries.iter().map(|e| (*e, 0)).collect();
+        let mut load: BTreeMap<Username, usize> = judges.iter().map(|j| (j.clone(), 0)).collect();
+        for (entry, judge) in &balanced {
+            *per_entry.get_mut(entry).unwrap() += 1;
+            *load.get_mut(judge).unwrap() += 1;
+        }
+
+        info!(contest = %contest, added = added.len(), removed = removed.len(), "rebalanced assignments");
+
+        Ok(Summary {
+            reviews_per_entry,
+            added: added.len(),
+            removed: removed.len(),
+            understaffed: per_entry.into_iter().filter(|(_, n)| *n < target).map(|(e, _)| e).collect(),
+            load,
+        })
+    }
+}
+
+/// Assigns `per_entry` judges to each entry (or as many as are eligible), keeping judges' loads
+/// within one of each other where exclusions allow. `existing` assignments are kept unless they
+/// are no longer valid or an entry has too many; those in `locked` are only dropped if invalid.
+fn balance<R: Rng>(
+    judges: &BTreeSet<Username>,
+    entries: &BTreeSet<ItemId>,
+    per_entry: usize,
+    existing: &BTreeSet<Pair>,
+    excluded: &BTreeSet<Pair>,
+    locked: &BTreeSet<Pair>,
+    rng: &mut R,
+) -> BTreeSet<Pair> {
+    let mut assigned: BTreeMap<ItemId, BTreeSet<Username>> = entries.iter().map(|e| (*e, BTreeSet::new())).collect();
+    let mut load: BTreeMap<&Username, usize> = judges.iter().map(|j| (j, 0)).collect();
+
+    for (entry, judge) in existing {
+        if let (Some(set), Some(l)) = (assigned.get_mut(entry), load.get_mut(judge)) {
+            if !excluded.contains(&(*entry, judge.clone())) {
+                set.insert(judge.clone());
+                *l += 1;
+            }
+        }
+    }
+
+    // Trim over-assigned entries, taking from the busiest judges first.
+    for (entry, set) in assigned.iter_mut() {
+        while set.len() > per_entry {
+            let drop = set.iter()
+                .filter(|j| !locked.contains(&(*entry, (*j).clone())))
+                .max_by_key(|j| load[j])
+                .cloned();
+            match drop {
+                Some(j) => {
+                    set.remove(&j);
+                    *load.get_mut(&j).unwrap() -= 1;
+                }
+                None => break,
+            }
+        }
+    }
+
+    let eligible = |entry: &ItemId, judge: &Username| !excluded.contains(&(*entry, judge.clone()));
+
+    // Most constrained entries first; shuffling first makes ties random.
+    let mut order: Vec<ItemId> = entries.iter().copied().collect();
+    order.shuffle(rng);
+    order.sort_by_key(|e| judges.iter().filter(|j| eligible(e, j)).count());
+
+    for entry in order {
+        let set = assigned.get_mut(&entry).unwrap();
+        let mut candidates: Vec<&Username> = judges.iter()
+            .filter(|j| eligible(&entry, j) && !set.contains(*j))
+            .collect();
+        candidates.shuffle(rng);
+        candidates.sort_by_key(|j| load[j]);
+
+        for judge in candidates.into_iter().take(per_entry.saturating_sub(set.len())) {
+            set.insert(judge.clone());
+            *load.get_mut(judge).unwrap() += 1;
+        }
+    }
+
+    // Move unstarted reviews from the busiest judges to the least busy until loads are level.
+    loop {
+        let mut by_load: Vec<&Username> = judges.iter().collect();
+        by_load.sort_by_key(|j| load[j]);
+
+        let movable = by_load.iter().rev()
+            .flat_map(|from| by_load.iter().map(move |to| (*from, *to)))
+            .filter(|(from, to)| load[from] > load[to] + 1)
+            .find_map(|(from, to)| {
+                assigned.iter()
+                    .find(|(entry, set)| {
+                        set.contains(from)
+                            && !set.contains(to)
+                            && eligible(entry, to)
+                            && !locked.contains(&(**entry, from.clone()))
+                    })
+                    .map(|(entry, _)| (*entry, from, to))
+            });
+
+        match movable {
+            Some((entry, from, to)) => {
+                let set = assigned.get_mut(&entry).unwrap();
+                set.remove(from);
+                set.insert(to.clone());
+                *load.get_mut(from).unwrap() -= 1;
+                *load.get_mut(to).unwrap() += 1;
+            }
+            None => break,
+        }
+    }
+
+    assigned.into_iter()
+        .flat_map(|(entry, set)| set.into_iter().map(move |j| (entry, j)))
+        .collect()
+}
@@ -0,0 +1,175 @@
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::{Contest, JudgingMode, Phase};
+use crate::model::entries::Entry;
+use crate::{db, api};
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("This contest isn't open for judging.")]
+    NotJudging,
+    #[error("This contest is not judged by ranked ballots.")]
+    WrongMode,
+    #[error("Entry {0} is not part of this contest.")]
+    UnknownEntry(ItemId),
+    #[error("Entry {0} is ranked more than once.")]
+    DuplicateEntry(ItemId),
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::NotJudging |
+            Error::WrongMode => Status::Conflict,
+            Error::UnknownEntry(_) |
+            Error::DuplicateEntry(_) => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+/// A judge's preference order over a contest's entries, best first. Entries left off are ranked
+/// below every listed entry.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ballot {
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub judge: Username,
+    pub ranking: Vec<ItemId>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BallotSubmission {
+    pub ranking: Vec<ItemId>,
+}
+
+impl Ballot {
+    /// Loads a judge's ballot. A judge who hasn't voted has an empty ranking.
+    pub async fn load(contest: ItemId, judge: &Username) -> db::Result<Self> {
+        let ranking = sqlx::query_scalar!(
+            r#"
+            SELECT entry as "entry: ItemId" FROM ballot_rankings
+            WHERE contest = $1 AND judge = $2
+            ORDER BY position;
+            "#,
+            *contest,
+            judge.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+
+        Ok(Self {
+            contest,
+            judge: judge.clone(),
+            ranking,
+        })
+    }
+
+    pub async fn all_for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT judge as "judge: Username", entry as "entry: ItemId" FROM ballot_rankings
+            WHERE contest = $1
+            ORDER BY judge, position;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+
+        let mut out: Vec<Self> = Vec::new();
+        for row in rows {
+            match out.last_mut() {
+                Some(b) if b.judge == row.judge => b.ranking.push(row.entry),
+                _ => out.push(Self { contest, judge: row.judge, ranking: vec![row.entry] }),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Checks that `ranking` only ranks entries from `entries`, and each of them once.
+    pub fn check_ranking(entries: &BTreeSet<ItemId>, ranking: &[ItemId]) -> Result<(), Error> {
+        let mut seen = BTreeSet::new();
+        for entry in ranking {
+            if !entries.contains(entry) {
+                return Err(Error::UnknownEntry(*entry));
+            }
+            if !seen.insert(*entry) {
+                return Err(Error::DuplicateEntry(*entry));
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces a judge's ballot.
+    pub async fn submit(contest: ItemId, judge: &Username, ranking: Vec<ItemId>) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        Self::ensure_open(contest, &mut tx).await?;
+
+        let entries = Entry::visible_ids(contest, judge).await?;
+        Self::check_ranking(&entries, &ranking)?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM ballot_rankings WHERE contest = $1 AND judge = $2;
+            "#,
+            *contest,
+            judge.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        let ids: Vec<i64> = ranking.iter().map(|e| **e).collect();
+        sqlx::query!(
+            r#"
+            INSERT INTO ballot_rankings (contest, judge, entry, position)
+            SELECT $1, $2, R.entry, (R.position - 1)::INT4
+            FROM UNNEST($3::INT8[]) WITH ORDINALITY AS R(entry, position);
+            "#,
+            *contest,
+            judge.as_str(),
+            &ids
+        ).execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(Self {
+            contest,
+            judge: judge.clone(),
+            ranking,
+        })
+    }
+
+    pub async fn withdraw(contest: ItemId, judge: &Username) -> api::Result<()> {
+        let mut tx = db::pool().begin().await?;
+        Self::ensure_open(contest, &mut tx).await?;
+
+        sqlx::query!(
+            r#"
+            DELETE FROM ballot_rankings WHERE contest = $1 AND judge = $2;
+            "#,
+            *contest,
+            judge.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn ensure_open(contest: ItemId, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> api::Result<()> {
+        let state = Contest::lock_state(contest, tx).await?;
+
+        if state.phase != Phase::Judging {
+            return Err(Error::NotJudging.into());
+        }
+        if state.mode != JudgingMode::Ballots {
+            return Err(Error::WrongMode.into());
+        }
+        Ok(())
+    }
+}
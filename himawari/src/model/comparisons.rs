@@ -0,0 +1,161 @@
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::{Contest, JudgingMode, Phase};
+use crate::model::entries::Entry;
+use crate::tally::pairwise;
+use crate::{db, api};
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("This contest isn't open for judging.")]
+    NotJudging,
+    #[error("This contest is not judged by pairwise comparisons.")]
+    WrongMode,
+    #[error("Entry {0} is not part of this contest.")]
+    UnknownEntry(ItemId),
+    #[error("An entry can't be compared with itself.")]
+    SameEntry,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::NotJudging |
+            Error::WrongMode => Status::Conflict,
+            Error::UnknownEntry(_) |
+            Error::SameEntry => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+/// A judge's pick between two entries.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Comparison {
+    pub id: ItemId,
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub judge: Username,
+    pub winner: ItemId,
+    pub loser: ItemId,
+    pub created: chrono::DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ComparisonSubmission {
+    pub winner: ItemId,
+    pub loser: ItemId,
+}
+
+/// Two entries for a judge to choose between.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Pair {
+    pub left: ItemId,
+    pub right: ItemId,
+}
+
+impl Comparison {
+    pub async fn all_for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Comparison,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", judge as "judge: _", winner as "winner: _", loser as "loser: _", created
+            FROM comparisons
+            WHERE contest = $1
+            ORDER BY id;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn for_judge(contest: ItemId, judge: &Username) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Comparison,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", judge as "judge: _", winner as "winner: _", loser as "loser: _", created
+            FROM comparisons
+            WHERE contest = $1 AND judge = $2
+            ORDER BY id;
+            "#,
+            *contest,
+            judge.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Checks that `winner` and `loser` are two different entries from `entries`.
+    pub fn check_pair(entries: &BTreeSet<ItemId>, winner: ItemId, loser: ItemId) -> Result<(), Error> {
+        if winner == loser {
+            return Err(Error::SameEntry);
+        }
+        for entry in &[winner, loser] {
+            if !entries.contains(entry) {
+                return Err(Error::UnknownEntry(*entry));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a judge's pick. A judge only gets one say on each pair, so comparing the same two
+    /// entries again replaces their earlier pick rather than counting twice.
+    pub async fn record(contest: ItemId, judge: &Username, submission: &ComparisonSubmission) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        let state = Contest::lock_state(contest, &mut tx).await?;
+        if state.phase != Phase::Judging {
+            return Err(Error::NotJudging.into());
+        }
+        if state.mode != JudgingMode::Pairwise {
+            return Err(Error::WrongMode.into());
+        }
+
+        let visible = Entry::visible_ids(contest, judge).await?;
+        Self::check_pair(&visible, submission.winner, submission.loser)?;
+
+        let out = sqlx::query_as!(
+            Comparison,
+            r#"
+            INSERT INTO comparisons (contest, judge, winner, loser) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (contest, judge, LEAST(winner, loser), GREATEST(winner, loser))
+                DO UPDATE SET winner = EXCLUDED.winner, loser = EXCLUDED.loser, created = now()
+            RETURNING id as "id: _", contest as "contest: _", judge as "judge: _", winner as "winner: _", loser as "loser: _", created;
+            "#,
+            *contest,
+            judge.as_str(),
+            *submission.winner,
+            *submission.loser
+        ).fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(out)
+    }
+
+    /// Chooses the next pair for `judge` from the entries they can see, or `None` if they have
+    /// compared every pair.
+    pub async fn next_pair(contest: &Contest, judge: &Username) -> api::Result<Option<Pair>> {
+        let judges = contest.judges().await?;
+        let entries = Entry::visible_ids(contest.id, judge).await?;
+        let results: Vec<(ItemId, ItemId)> = Self::all_for_contest(contest.id).await?
+            .into_iter()
+            .filter(|c| judges.contains(&c.judge))
+            .map(|c| (c.winner, c.loser))
+            .collect();
+        let seen: BTreeSet<(ItemId, ItemId)> = Self::for_judge(contest.id, judge).await?
+            .into_iter()
+            .map(|c| (c.winner, c.loser))
+            .collect();
+
+        let pair = pairwise::next_pair(&entries, &results, &seen, &mut rand::thread_rng());
+        Ok(pair.map(|(left, right)| Pair { left, right }))
+    }
+}
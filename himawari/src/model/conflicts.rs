@@ -0,0 +1,200 @@
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::Contest;
+use crate::model::entries::Entry;
+use crate::model::assignments::Assignment;
+use crate::{db, api};
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("{0} is not a judge of this contest.")]
+    NotAJudge(Username),
+    #[error("Entry {0} is not part of this contest.")]
+    UnknownEntry(ItemId),
+    #[error("This contest is judged blind, so only the owner can declare conflicts with a creator. Declare one with the entry instead.")]
+    CreatorHidden,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::NotAJudge(_) |
+            Error::UnknownEntry(_) => Status::BadRequest,
+            Error::CreatorHidden => Status::Forbidden,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+/// A judge who must not see or judge an entry, or anything entered by a given creator.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Conflict {
+    pub id: ItemId,
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub judge: Username,
+    #[serde(rename = "entryId")]
+    pub entry: Option<ItemId>,
+    pub creator: Option<String>,
+    pub reason: Option<String>,
+    #[serde(rename = "declaredBy")]
+    pub declared_by: Username,
+    pub created: chrono::DateTime<Utc>,
+}
+
+/// Exactly one of `entry` and `creator` must be given. `judge` defaults to the caller; only the
+/// contest owner may declare conflicts for someone else, or with a creator in a blind contest.
+#[derive(Deserialize, Clone, Debug, Validate)]
+#[validate(schema(function = "validate_target"))]
+pub struct NewConflict {
+    #[serde(default)]
+    pub judge: Option<Username>,
+    #[serde(rename = "entryId", default)]
+    pub entry: Option<ItemId>,
+    #[validate(length(min = 1, max = 1024), non_control_character)]
+    #[serde(default)]
+    pub creator: Option<String>,
+    #[validate(length(max = 1024))]
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+fn validate_target(fields: &NewConflict) -> Result<(), ValidationError> {
+    if fields.entry.is_some() != fields.creator.is_some() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("exactly one of entryId and creator is required"))
+    }
+}
+
+impl Conflict {
+    pub async fn load(id: ItemId) -> db::Result<Self> {
+        let out = sqlx::query_as!(
+            Conflict,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", judge as "judge: _", entry as "entry: _", creator, reason,
+                   declared_by as "declared_by: _", created
+            FROM conflicts
+            WHERE id = $1;
+            "#,
+            *id
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Conflict,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", judge as "judge: _", entry as "entry: _", creator, reason,
+                   declared_by as "declared_by: _", created
+            FROM conflicts
+            WHERE contest = $1
+            ORDER BY id;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn for_judge(contest: ItemId, judge: &Username) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Conflict,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", judge as "judge: _", entry as "entry: _", creator, reason,
+                   declared_by as "declared_by: _", created
+            FROM conflicts
+            WHERE contest = $1 AND judge = $2
+            ORDER BY id;
+            "#,
+            *contest,
+            judge.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Every `(entry, judge)` pair in the contest where the judge is conflicted, including judges'
+    /// own entries. Judgments for these pairs are left out of results.
+    pub async fn excluded_pairs(contest: ItemId) -> db::Result<BTreeSet<(ItemId, Username)>> {
+        let out = sqlx::query!(
+            r#"
+            SELECT entry as "entry!: ItemId", judge as "judge!: Username" FROM assignment_exclusions($1);
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?
+            .into_iter()
+            .map(|r| (r.entry, r.judge))
+            .collect();
+        Ok(out)
+    }
+
+    /// Records a conflict for `judge`. If the contest uses assignments, they are rebalanced so the
+    /// judge's conflicted entries go to someone else.
+    pub async fn declare(contest: &Contest, judge: &Username, declared_by: &Username, conflict: &NewConflict) -> api::Result<Self> {
+        if !contest.judges().await?.contains(judge) {
+            return Err(Error::NotAJudge(judge.clone()).into());
+        }
+        if let Some(entry) = conflict.entry {
+            match Entry::load(entry).await {
+                Ok(e) if e.contest == contest.id => {}
+                Ok(_) | Err(db::Error::NotFound) => return Err(Error::UnknownEntry(entry).into()),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let out = sqlx::query_as!(
+            Conflict,
+            r#"
+            INSERT INTO conflicts (contest, judge, entry, creator, reason, declared_by) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id as "id: _", contest as "contest: _", judge as "judge: _", entry as "entry: _", creator, reason,
+                      declared_by as "declared_by: _", created;
+            "#,
+            *contest.id,
+            judge.as_str(),
+            conflict.entry.map(|e| *e),
+            conflict.creator,
+            conflict.reason,
+            declared_by.as_str()
+        ).fetch_one(db::pool())
+            .await?;
+
+        info!(contest = %contest.id, judge = %judge, declared_by = %declared_by, "declared conflict of interest");
+
+        if Assignment::enabled(contest.id).await? {
+            Assignment::rebalance(contest).await?;
+        }
+
+        Ok(out)
+    }
+
+    /// Removes a conflict. If the contest uses assignments, they are rebalanced so the judge can
+    /// be given the entries it kept from them.
+    pub async fn withdraw(id: ItemId) -> api::Result<()> {
+        let contest = sqlx::query_scalar!(
+            r#"
+            DELETE FROM conflicts WHERE id = $1 RETURNING contest as "contest: ItemId";
+            "#,
+            *id
+        ).fetch_optional(db::pool())
+            .await?
+            .ok_or(db::Error::NotFound)?;
+
+        if Assignment::enabled(contest).await? {
+            Assignment::rebalance(&Contest::load(contest).await?).await?;
+        }
+
+        Ok(())
+    }
+}
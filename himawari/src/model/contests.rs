@@ -7,8 +7,347 @@ use rocket::futures::TryStreamExt;
 
 use crate::secure::GuardedResource;
 use crate::secure;
+use crate::model::assignments::Assignment;
 use crate::model::users::Info;
 use rocket::http::Status;
+use std::borrow::Cow;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("A contest can't go from {0} to {1}.")]
+    BadTransition(Phase, Phase),
+    #[error("That can only be done while the contest is in the {0} phase.")]
+    WrongPhase(Phase),
+    #[error("The {0} deadline has already passed. Move or clear it first.")]
+    DeadlinePassed(Phase),
+    #[error("Someone else changed this contest since the version you're editing. Reload it and try again.")]
+    Stale,
+    #[error("The contest no longer matches the version in If-Match. Reload it and try again.")]
+    PreconditionFailed,
+    #[error("Updates must say which version of the contest they're based on, with If-Match or a version field.")]
+    VersionRequired,
+    #[error("That cursor is invalid or belongs to a different sort order.")]
+    BadCursor,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::BadTransition(_, _) |
+            Error::WrongPhase(_) |
+            Error::DeadlinePassed(_) |
+            Error::Stale => Status::Conflict,
+            Error::PreconditionFailed => Status::PreconditionFailed,
+            Error::VersionRequired => Status::PreconditionRequired,
+            Error::BadCursor => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+/// Where a contest is in its life, in order.
+#[derive(sqlx::Type, rocket::FromFormField, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[sqlx(type_name = "contest_phase", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Being set up; only the owner can see anything happen.
+    Draft,
+    /// Accepting entries.
+    Submissions,
+    /// Accepting scores, ballots or comparisons.
+    Judging,
+    /// Judging is over and the owner is reviewing results.
+    Deliberation,
+    /// Results are visible to every judge.
+    Published,
+    Archived,
+}
+
+serde_plain::forward_display_to_serde!(Phase);
+
+impl Phase {
+    /// Whether a contest may move straight from this phase to `next`. The only step backwards is
+    /// reopening judging during deliberation.
+    pub fn can_become(self, next: Phase) -> bool {
+        use Phase::*;
+        matches!(
+            (self, next),
+            (Draft, Submissions) |
+            (Submissions, Judging) |
+            (Judging, Deliberation) |
+            (Deliberation, Judging) |
+            (Deliberation, Published) |
+            (Published, Archived)
+        )
+    }
+}
+
+/// What judges submit for a contest.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "judging_mode", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JudgingMode {
+    /// Per-criterion rubric scores for each entry.
+    Scores,
+    /// An ordered list of entries from each judge.
+    Ballots,
+    /// Head-to-head picks between pairs of entries.
+    Pairwise,
+}
+
+serde_plain::forward_display_to_serde!(JudgingMode);
+
+/// Who can see a contest besides its owner and judges.
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "contest_visibility", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Visibility {
+    /// Nobody else.
+    Private,
+    /// Anyone with a link, but it isn't listed anywhere.
+    Unlisted,
+    /// Anyone, and it's listed publicly.
+    Public,
+}
+
+impl Default for Visibility {
+    fn default() -> Self {
+        Visibility::Private
+    }
+}
+
+serde_plain::forward_display_to_serde!(Visibility);
+
+/// The parts of a contest that decide what may be submitted to it.
+#[derive(Debug, Clone, Copy)]
+pub struct ContestState {
+    pub phase: Phase,
+    pub mode: JudgingMode,
+}
+
+/// How long a deleted contest stays in the trash before it's purged.
+pub const TRASH_RETENTION_DAYS: i32 = 30;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrashedContest {
+    #[serde(flatten)]
+    pub contest: Contest,
+    pub deleted: chrono::DateTime<Utc>,
+    #[serde(rename = "purgeAfter")]
+    pub purge_after: chrono::DateTime<Utc>,
+}
+
+/// Someone other than the owner who can judge a contest.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Judge {
+    pub username: Username,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+}
+
+/// When a contest automatically leaves the submissions and judging phases. Each deadline is
+/// cleared once it has moved the contest on.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_deadlines"))]
+pub struct Deadlines {
+    #[serde(rename = "submissionDeadline", default)]
+    pub submission_deadline: Option<chrono::DateTime<Utc>>,
+    #[serde(rename = "judgingDeadline", default)]
+    pub judging_deadline: Option<chrono::DateTime<Utc>>,
+}
+
+/// Works out which version of the contest an update is based on, from its `If-Match` header or
+/// failing that the `version` in its body. Also returns the error to give if that version turns out
+/// to be stale, which depends on where it came from.
+pub fn expected_version(if_match: Option<i64>, body: Option<i64>) -> Result<(i64, Error), Error> {
+    match (if_match, body) {
+        (Some(v), _) => Ok((v, Error::PreconditionFailed)),
+        (None, Some(v)) => Ok((v, Error::Stale)),
+        (None, None) => Err(Error::VersionRequired),
+    }
+}
+
+fn validate_deadlines(deadlines: &Deadlines) -> Result<(), ValidationError> {
+    match (deadlines.submission_deadline, deadlines.judging_deadline) {
+        (Some(submission), Some(judging)) if submission >= judging => {
+            Err(ValidationError::new("submissionDeadline must be before judgingDeadline"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// What users need before they can take part in a contest as a judge or an entrant.
+#[derive(sqlx::FromRow, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Requirements {
+    #[serde(rename = "verifiedEmail", default)]
+    pub verified_email: bool,
+}
+
+/// A record of a contest moving between phases.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct PhaseChange {
+    pub from: Phase,
+    pub to: Phase,
+    /// `None` if the change was made automatically.
+    #[serde(rename = "changedBy")]
+    pub changed_by: Option<Username>,
+    pub changed: chrono::DateTime<Utc>,
+}
+
+/// Which of a user's contests to list.
+#[derive(rocket::FromFormField, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Relation {
+    All,
+    /// Contests the user owns.
+    Owned,
+    /// Contests the user judges but doesn't own.
+    Judging,
+}
+
+impl Default for Relation {
+    fn default() -> Self {
+        Relation::All
+    }
+}
+
+serde_plain::forward_display_to_serde!(Relation);
+
+#[derive(rocket::FromFormField, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sort {
+    Newest,
+    Oldest,
+    #[field(value = "name")]
+    #[serde(rename = "name")]
+    NameAsc,
+    #[field(value = "name_desc")]
+    NameDesc,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort::Newest
+    }
+}
+
+serde_plain::forward_display_to_serde!(Sort);
+
+/// Filters, sort order and position for a page of the contest list.
+#[derive(rocket::FromForm, Debug, Clone, Validate)]
+pub struct ContestQuery {
+    #[field(default = Relation::All)]
+    pub relation: Relation,
+    pub phase: Option<Phase>,
+    /// Only contests whose names contain this, ignoring case.
+    #[validate(length(min = 1, max = 1024))]
+    pub q: Option<String>,
+    #[field(default = Sort::Newest)]
+    pub sort: Sort,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<i64>,
+    /// The `nextCursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+/// Where the previous page ended. Opaque to clients.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Cursor {
+    sort: Sort,
+    id: ItemId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        hex::encode(serde_json::to_vec(self).expect("cursors always serialize"))
+    }
+
+    fn decode(s: &str, sort: Sort) -> Result<Self, Error> {
+        let cursor: Cursor = hex::decode(s).ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .ok_or(Error::BadCursor)?;
+        let keyed_by_name = matches!(sort, Sort::NameAsc | Sort::NameDesc);
+        if cursor.sort != sort || cursor.name.is_some() != keyed_by_name {
+            return Err(Error::BadCursor);
+        }
+        Ok(cursor)
+    }
+}
+
+/// How many contests a page holds when the query doesn't say.
+pub const DEFAULT_PAGE_SIZE: i64 = 20;
+
+impl ContestQuery {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+
+    /// The position to continue from as `(id, name)`, or `None` for the first page.
+    pub fn after(&self) -> Result<Option<(ItemId, Option<String>)>, Error> {
+        self.cursor.as_deref()
+            .map(|c| Cursor::decode(c, self.sort).map(|c| (c.id, c.name)))
+            .transpose()
+    }
+
+    /// A `LIKE` pattern matching names that contain `q`.
+    pub fn name_pattern(&self) -> Option<String> {
+        self.q.as_ref().map(|q| {
+            let escaped = q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{}%", escaped)
+        })
+    }
+
+    /// The cursor for the page after one ending with `last`.
+    pub fn cursor_after(&self, last: &Contest) -> String {
+        let name = match self.sort {
+            Sort::NameAsc | Sort::NameDesc => Some(last.name.clone()),
+            Sort::Newest | Sort::Oldest => None,
+        };
+        Cursor { sort: self.sort, id: last.id, name }.encode()
+    }
+}
+
+/// What the contest list shows for a contest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ContestSummary {
+    #[serde(flatten)]
+    pub contest: Contest,
+    #[serde(rename = "entryCount")]
+    pub entry_count: i64,
+    /// Including the owner.
+    #[serde(rename = "judgeCount")]
+    pub judge_count: i64,
+    /// The caller's role in the contest.
+    pub role: secure::Role,
+    pub progress: Progress,
+}
+
+/// How far someone is through judging a contest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Progress {
+    /// Entries they've scored, ranked or compared at least once, depending on the judging mode.
+    pub judged: i64,
+    /// Entries they're meant to judge: every entry they can see as a judge.
+    #[serde(rename = "toJudge")]
+    pub to_judge: i64,
+}
+
+/// One page of a list.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass back as `cursor` to get the next page. `None` on the last page.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+    /// How many items match the filters, across every page.
+    pub total: i64,
+}
 
 #[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
 pub struct Contest {
@@ -16,9 +355,31 @@ pub struct Contest {
     pub owner: Username,
     pub name: String,
     pub created: chrono::DateTime<Utc>,
+    pub phase: Phase,
+    #[serde(rename = "phaseChanged")]
+    pub phase_changed: chrono::DateTime<Utc>,
+    /// Goes up by one whenever the contest changes. Sent back as an `ETag`.
+    pub version: i64,
+    pub visibility: Visibility,
 }
 
 impl Contest {
+    /// Public contests, newest first. Unlisted ones are left out.
+    pub async fn public() -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Contest,
+            r#"
+            SELECT id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                   visibility as "visibility: _"
+            FROM contests
+            WHERE visibility = 'public' AND deleted IS NULL
+            ORDER BY id DESC;
+            "#
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
     pub async fn judges(&self) -> db::Result<BTreeSet<Username>> {
         let q: sqlx::query::QueryScalar<_, _, _> = sqlx::query_scalar!(
             r#"
@@ -36,6 +397,77 @@ impl Contest {
         Ok(judges)
     }
 
+    /// The contest's judges, not counting the owner.
+    pub async fn invited_judges(&self) -> db::Result<Vec<Judge>> {
+        let out = sqlx::query_as!(
+            Judge,
+            r#"
+            SELECT U.username as "username: _", U.display_name
+            FROM contest_judges CJ JOIN users U ON U.username = CJ.judge
+            WHERE CJ.contest = $1
+            ORDER BY U.username;
+            "#,
+            *self.id
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Makes `username` a judge of the contest, rebalancing assignments if the contest uses them.
+    /// The owner always judges their own contest, so adding them is a conflict.
+    pub async fn add_judge(&self, username: &Username) -> api::Result<Judge> {
+        if *username == self.owner {
+            return Err(db::Error::Conflict.into());
+        }
+
+        let out = sqlx::query_as!(
+            Judge,
+            r#"
+            WITH added AS (
+                INSERT INTO contest_judges (contest, judge)
+                SELECT $1, username FROM users WHERE username = $2
+                RETURNING judge
+            )
+            SELECT U.username as "username!: _", U.display_name as "display_name!"
+            FROM added A JOIN users U ON U.username = A.judge;
+            "#,
+            *self.id,
+            username.as_str()
+        ).fetch_one(db::pool())
+            .await?;
+
+        info!(contest = %self.id, judge = %username, "added judge");
+
+        if Assignment::enabled(self.id).await? {
+            Assignment::rebalance(self).await?;
+        }
+        Ok(out)
+    }
+
+    /// Removes `username` from the contest's judges, rebalancing assignments if the contest uses
+    /// them. Their judgments stay in the database but no longer count towards results.
+    pub async fn remove_judge(&self, username: &Username) -> api::Result<()> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM contest_judges WHERE contest = $1 AND judge = $2;
+            "#,
+            *self.id,
+            username.as_str()
+        ).execute(db::pool())
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound.into());
+        }
+
+        info!(contest = %self.id, judge = %username, "removed judge");
+
+        if Assignment::enabled(self.id).await? {
+            Assignment::rebalance(self).await?;
+        }
+        Ok(())
+    }
+
     pub async fn entries(&self) -> db::Result<BTreeSet<ItemId>> {
         let q = sqlx::query_scalar!(
             r#"
@@ -55,7 +487,10 @@ impl Contest {
         let out = sqlx::query_as!(
             Contest,
             r#"
-            SELECT id as "id: _", owner as "owner: _", name, created FROM contests WHERE id = $1;
+            SELECT id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                   visibility as "visibility: _"
+            FROM contests
+            WHERE id = $1;
             "#,
             *id
         ).fetch_one(db::pool())
@@ -63,10 +498,198 @@ impl Contest {
         Ok(out)
     }
 
-    pub async fn delete(id: ItemId) -> db::Result<()> {
+    /// Updates the contest, as long as it's still at `version`. Returns `None` if someone else
+    /// changed it first.
+    pub async fn update(id: ItemId, name: &str, visibility: Visibility, version: i64) -> db::Result<Option<Self>> {
+        let out = sqlx::query_as!(
+            Contest,
+            r#"
+            UPDATE contests SET name = $2, visibility = $3 WHERE id = $1 AND version = $4
+            RETURNING id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                   visibility as "visibility: _";
+            "#,
+            *id,
+            name,
+            visibility as Visibility,
+            version
+        ).fetch_optional(db::pool())
+            .await?;
+
+        if out.is_none() {
+            // Tells a missing contest apart from a stale version.
+            Self::load(id).await?;
+        }
+        Ok(out)
+    }
+
+    /// Reads the contest's phase and judging mode and holds a share lock on the contest for the
+    /// rest of `tx`, so the phase can't change while something that depends on it is written.
+    pub async fn lock_state(id: ItemId, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> db::Result<ContestState> {
+        let out = sqlx::query_as!(
+            ContestState,
+            r#"
+            SELECT phase as "phase: _", judging_mode as "mode: _" FROM contests WHERE id = $1 FOR SHARE;
+            "#,
+            *id
+        ).fetch_one(&mut *tx)
+            .await?;
+        Ok(out)
+    }
+
+    /// Moves the contest to phase `to`, if that's allowed from its current phase. `by` is the user
+    /// who asked for the change.
+    pub async fn change_phase(id: ItemId, to: Phase, by: &Username) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        let (from, out) = Self::change_phase_in(id, to, Some(by), &mut tx).await?;
+        tx.commit().await?;
+
+        info!(contest = %id, %from, %to, by = %by, "contest changed phase");
+        Ok(out)
+    }
+
+    /// Does the work of [`change_phase`](Contest::change_phase) inside `tx`, returning the phase
+    /// the contest was in. `by` is `None` for automatic changes, which use up the deadline that
+    /// caused them. Nobody can move a contest into a phase whose deadline has passed, or it would
+    /// immediately be moved on again.
+    pub async fn change_phase_in(id: ItemId, to: Phase, by: Option<&Username>, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> api::Result<(Phase, Self)> {
+        let current = sqlx::query!(
+            r#"
+            SELECT phase as "phase: Phase",
+                   CASE $2::contest_phase
+                       WHEN 'submissions' THEN submission_deadline <= now()
+                       WHEN 'judging' THEN judging_deadline <= now()
+                   END as "deadline_passed"
+            FROM contests WHERE id = $1 FOR UPDATE;
+            "#,
+            *id,
+            to as Phase
+        ).fetch_one(&mut *tx)
+            .await?;
+        let from = current.phase;
+
+        if !from.can_become(to) {
+            return Err(Error::BadTransition(from, to).into());
+        }
+        if by.is_some() && current.deadline_passed == Some(true) {
+            return Err(Error::DeadlinePassed(to).into());
+        }
+
+        let out = sqlx::query_as!(
+            Contest,
+            r#"
+            UPDATE contests
+            SET phase = $2, phase_changed = now(),
+                submission_deadline = CASE WHEN $3 AND phase = 'submissions' THEN NULL ELSE submission_deadline END,
+                judging_deadline = CASE WHEN $3 AND phase = 'judging' THEN NULL ELSE judging_deadline END
+            WHERE id = $1
+            RETURNING id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                   visibility as "visibility: _";
+            "#,
+            *id,
+            to as Phase,
+            by.is_none()
+        ).fetch_one(&mut *tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO contest_phase_changes (contest, from_phase, to_phase, changed_by, changed) VALUES ($1, $2, $3, $4, $5);
+            "#,
+            *id,
+            from as Phase,
+            to as Phase,
+            by.map(|u| u.as_str()),
+            out.phase_changed
+        ).execute(&mut *tx)
+            .await?;
+
+        Ok((from, out))
+    }
+
+    pub async fn deadlines(id: ItemId) -> db::Result<Deadlines> {
+        let out = sqlx::query_as!(
+            Deadlines,
+            r#"
+            SELECT submission_deadline, judging_deadline FROM contests WHERE id = $1;
+            "#,
+            *id
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Replaces the contest's deadlines, as long as it's still at `version`. Returns the new version,
+    /// or `None` if someone else changed the contest first.
+    pub async fn set_deadlines(id: ItemId, deadlines: &Deadlines, version: i64) -> db::Result<Option<i64>> {
+        let out = sqlx::query_scalar!(
+            r#"
+            UPDATE contests SET submission_deadline = $2, judging_deadline = $3 WHERE id = $1 AND version = $4
+            RETURNING version;
+            "#,
+            *id,
+            deadlines.submission_deadline,
+            deadlines.judging_deadline,
+            version
+        ).fetch_optional(db::pool())
+            .await?;
+
+        if out.is_none() {
+            // Tells a missing contest apart from a stale version.
+            Self::load(id).await?;
+        }
+        Ok(out)
+    }
+
+    pub async fn requirements(id: ItemId) -> db::Result<Requirements> {
+        let out = sqlx::query_as!(
+            Requirements,
+            r#"
+            SELECT require_verified_email as verified_email FROM contests WHERE id = $1;
+            "#,
+            *id
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn set_requirements(id: ItemId, requirements: &Requirements) -> db::Result<()> {
         let res = sqlx::query!(
             r#"
-            DELETE FROM contests WHERE id = $1;
+            UPDATE contests SET require_verified_email = $2 WHERE id = $1;
+            "#,
+            *id,
+            requirements.verified_email
+        ).execute(db::pool())
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn phase_history(id: ItemId) -> db::Result<Vec<PhaseChange>> {
+        let out = sqlx::query_as!(
+            PhaseChange,
+            r#"
+            SELECT from_phase as "from: _", to_phase as "to: _", changed_by as "changed_by: _", changed
+            FROM contest_phase_changes
+            WHERE contest = $1
+            ORDER BY id;
+            "#,
+            *id
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Moves the contest to the trash. It disappears for everyone, and is purged for good after
+    /// [`TRASH_RETENTION_DAYS`] unless the owner restores it.
+    pub async fn trash(id: ItemId) -> db::Result<()> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE contests SET deleted = now() WHERE id = $1 AND deleted IS NULL;
             "#,
             *id
         ).execute(db::pool())
@@ -76,8 +699,74 @@ impl Contest {
             return Err(db::Error::NotFound);
         }
 
+        info!(contest = %id, "moved contest to trash");
         Ok(())
     }
+
+    /// `owner`'s contests that are in the trash and not yet purged, most recently deleted first.
+    pub async fn trashed(owner: &Username) -> db::Result<Vec<TrashedContest>> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id as "id: ItemId", owner as "owner: Username", name, created, phase as "phase: Phase", phase_changed, version,
+                   visibility as "visibility: Visibility",
+                   deleted as "deleted!", deleted + make_interval(days => $2) as "purge_after!"
+            FROM contests
+            WHERE owner = $1 AND deleted IS NOT NULL
+            ORDER BY deleted DESC;
+            "#,
+            owner.as_str(),
+            TRASH_RETENTION_DAYS
+        ).fetch_all(db::pool())
+            .await?;
+
+        let out = rows.into_iter()
+            .map(|r| TrashedContest {
+                contest: Contest {
+                    id: r.id,
+                    owner: r.owner,
+                    name: r.name,
+                    created: r.created,
+                    phase: r.phase,
+                    phase_changed: r.phase_changed,
+                    version: r.version,
+                    visibility: r.visibility,
+                },
+                deleted: r.deleted,
+                purge_after: r.purge_after,
+            })
+            .collect();
+        Ok(out)
+    }
+
+    /// Takes one of `owner`'s contests back out of the trash.
+    pub async fn restore(id: ItemId, owner: &Username) -> db::Result<Self> {
+        let out = sqlx::query_as!(
+            Contest,
+            r#"
+            UPDATE contests SET deleted = NULL WHERE id = $1 AND owner = $2 AND deleted IS NOT NULL
+            RETURNING id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                   visibility as "visibility: _";
+            "#,
+            *id,
+            owner.as_str()
+        ).fetch_one(db::pool())
+            .await?;
+
+        info!(contest = %id, "restored contest from trash");
+        Ok(out)
+    }
+
+    /// Permanently deletes contests that have been in the trash longer than the retention window.
+    pub async fn purge_expired() -> db::Result<u64> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM contests WHERE deleted < now() - make_interval(days => $1);
+            "#,
+            TRASH_RETENTION_DAYS
+        ).execute(db::pool())
+            .await?;
+        Ok(res.rows_affected())
+    }
 }
 
 #[async_trait::async_trait]
@@ -98,4 +787,18 @@ impl GuardedResource for Contest {
 
         Ok(role)
     }
+
+    async fn anonymous_access_level(rid: &Self::ResourceId) -> api::Result<secure::Role> {
+        let role: Option<secure::Role> = sqlx::query_scalar!(
+            r#"
+            SELECT role AS "role: secure::Role" FROM contest_access_for_anonymous($1);
+            "#,
+            rid.as_ref()
+        ).fetch_one(db::pool())
+            .await?;
+
+        let role = role.ok_or(Status::NotFound)?;
+
+        Ok(role)
+    }
 }
\ No newline at end of file
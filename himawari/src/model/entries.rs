@@ -0,0 +1,371 @@
+use crate::model::ItemId;
+use crate::{db, api};
+use crate::secure::GuardedResource;
+use crate::secure;
+use crate::model::users::Info;
+use rocket::http::Status;
+use serde::{Deserializer, Deserialize};
+use crate::model::users::Username;
+use crate::model::contests::{self, Contest, Phase};
+use crate::model::assignments::Assignment;
+use crate::model::verification::EmailVerification;
+use rocket::futures::TryStreamExt;
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use jsonschema::JSONSchema;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("The entry schema is invalid: {0}")]
+    InvalidSchema(String),
+    #[error("The entry schema must describe an object.")]
+    SchemaNotObject,
+    #[error("Invalid custom fields: {}", .0.join("; "))]
+    InvalidFields(Vec<String>),
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::InvalidSchema(_) |
+            Error::SchemaNotObject |
+            Error::InvalidFields(_) => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: ItemId,
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub name: String,
+    pub creator: String,
+    pub url: Option<String>,
+    pub description: Option<String>,
+    /// Values for the contest's own fields, as described by its [`EntrySchema`].
+    #[serde(rename = "customFields")]
+    pub custom_fields: serde_json::Value,
+}
+
+/// The user-editable parts of an entry.
+#[derive(Deserialize, Clone, Debug, validator::Validate)]
+pub struct EntryFields {
+    #[validate(length(min = 1, max = 1024), non_control_character)]
+    pub name: String,
+    #[validate(length(min = 1, max = 1024), non_control_character)]
+    pub creator: String,
+    #[validate(length(max = 1024), url)]
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub url: Option<String>,
+    #[validate(length(max = 65535))]
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub description: Option<String>,
+    #[serde(rename = "customFields", default = "no_custom_fields")]
+    pub custom_fields: serde_json::Value,
+}
+
+/// A JSON Schema describing the extra fields a contest collects for each entry. Contests without
+/// one only accept entries with no custom fields. A top-level property marked
+/// `"x-identifying": true` could give away who made the entry, so it's hidden when the contest is
+/// judged blind.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct EntrySchema {
+    #[serde(default)]
+    pub schema: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Clone, Debug, validator::Validate)]
+pub struct NewEntry {
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    #[serde(flatten)]
+    #[validate]
+    pub fields: EntryFields,
+}
+
+/// Shown in place of the creator when a contest is judged blind.
+const ANONYMOUS_CREATOR: &str = "Anonymous";
+
+/// The frontend sends empty strings for optional fields that were left blank.
+fn empty_as_none<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    let s: Option<String> = Option::deserialize(d)?;
+    Ok(s.filter(|s| !s.is_empty()))
+}
+
+fn no_custom_fields() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+fn compile(schema: &serde_json::Value) -> Result<JSONSchema, Error> {
+    if schema.get("type").and_then(|t| t.as_str()) != Some("object") {
+        return Err(Error::SchemaNotObject);
+    }
+    JSONSchema::compile(schema).map_err(|e| Error::InvalidSchema(e.to_string()))
+}
+
+impl EntrySchema {
+    pub async fn load(contest: ItemId) -> db::Result<Self> {
+        let schema = sqlx::query_scalar!(
+            r#"
+            SELECT entry_schema FROM contests WHERE id = $1;
+            "#,
+            *contest
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(Self { schema })
+    }
+
+    /// Replaces the contest's schema. Existing entries aren't revalidated until they're next
+    /// updated.
+    pub async fn save(&self, contest: ItemId) -> api::Result<()> {
+        self.ensure_valid()?;
+
+        let res = sqlx::query!(
+            r#"
+            UPDATE contests SET entry_schema = $2 WHERE id = $1;
+            "#,
+            *contest,
+            self.schema
+        ).execute(db::pool())
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound.into());
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the schema itself is usable.
+    pub fn ensure_valid(&self) -> Result<(), Error> {
+        match &self.schema {
+            Some(schema) => compile(schema).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Top-level fields the schema marks as identifying the entrant.
+    pub fn identifying_fields(&self) -> BTreeSet<String> {
+        self.schema.as_ref()
+            .and_then(|s| s.get("properties"))
+            .and_then(|p| p.as_object())
+            .map(|properties| properties.iter()
+                .filter(|(_, p)| p.get("x-identifying").and_then(|x| x.as_bool()) == Some(true))
+                .map(|(name, _)| name.clone())
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// Checks `fields` against the schema, reporting every value that fails and where it is.
+    pub fn check(&self, fields: &serde_json::Value) -> Result<(), Error> {
+        let schema = match &self.schema {
+            Some(schema) => compile(schema)?,
+            None if fields.as_object().map_or(false, |o| o.is_empty()) => return Ok(()),
+            None => return Err(Error::InvalidFields(vec!["this contest has no custom fields".to_string()])),
+        };
+
+        schema.validate(fields).map_err(|errors| {
+            Error::InvalidFields(errors.map(|e| {
+                let path = e.instance_path.to_string();
+                format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+            }).collect())
+        })
+    }
+}
+
+impl Entry {
+    /// Adds an entry to a contest that is accepting submissions. If the contest uses assignments,
+    /// they are rebalanced so the new entry has judges who can see it.
+    pub async fn create(entry: &NewEntry) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        let state = Contest::lock_state(entry.contest, &mut tx).await?;
+        if state.phase != Phase::Submissions {
+            return Err(contests::Error::WrongPhase(Phase::Submissions).into());
+        }
+        EntrySchema::load(entry.contest).await?.check(&entry.fields.custom_fields)?;
+        EmailVerification::ensure_entrant_allowed(entry.contest, &entry.fields.creator).await?;
+
+        let out = sqlx::query_as!(
+            Entry,
+            r#"
+            INSERT INTO entries (contest, name, creator, url, description, custom_fields) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id as "id: _", contest as "contest: _", name, creator, url, description, custom_fields;
+            "#,
+            *entry.contest,
+            entry.fields.name,
+            entry.fields.creator,
+            entry.fields.url,
+            entry.fields.description,
+            entry.fields.custom_fields
+        ).fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+
+        if Assignment::enabled(entry.contest).await? {
+            Assignment::rebalance(&Contest::load(entry.contest).await?).await?;
+        }
+
+        Ok(out)
+    }
+
+    pub async fn load(id: ItemId) -> db::Result<Self> {
+        let out = sqlx::query_as!(
+            Entry,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", name, creator, url, description, custom_fields FROM entries WHERE id = $1;
+            "#,
+            *id
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// The contest's entries that `user` (or an anonymous viewer, if `None`) is allowed to see.
+    pub async fn visible_to(contest: ItemId, user: Option<&Username>) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Entry,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", name, creator, url, description, custom_fields
+            FROM entries
+            WHERE contest = $1
+              AND CASE WHEN $2::VARCHAR IS NULL THEN entry_access_for_anonymous(id)
+                       ELSE entry_access_for_user($2, id) END >= 'viewer'
+            ORDER BY id;
+            "#,
+            *contest,
+            user.map(|u| u.as_str())
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Ids of the contest's entries that `user` is allowed to see.
+    pub async fn visible_ids(contest: ItemId, user: &Username) -> db::Result<BTreeSet<ItemId>> {
+        let out = sqlx::query_scalar!(
+            r#"
+            SELECT id as "id: ItemId"
+            FROM entries
+            WHERE contest = $1 AND entry_access_for_user($2, id) >= 'collaborator'
+            ORDER BY id;
+            "#,
+            *contest,
+            user.as_str()
+        ).fetch(db::pool())
+            .try_collect()
+            .await?;
+        Ok(out)
+    }
+
+    /// Removes everything that identifies who made the entry, including the custom fields
+    /// `schema` marks as [identifying](EntrySchema::identifying_fields).
+    pub fn blinded(self, schema: &EntrySchema) -> Self {
+        let mut custom_fields = self.custom_fields;
+        if let Some(fields) = custom_fields.as_object_mut() {
+            for name in schema.identifying_fields() {
+                fields.remove(&name);
+            }
+        }
+
+        Self {
+            creator: ANONYMOUS_CREATOR.to_string(),
+            url: None,
+            custom_fields,
+            ..self
+        }
+    }
+
+    /// Whether entries of `contest` must be [blinded](Entry::blinded) before they're shown to
+    /// someone with `role`. Owners and above always see who made an entry.
+    pub async fn identity_hidden_from(contest: ItemId, role: &secure::Role) -> db::Result<bool> {
+        if *role >= secure::Role::Owner {
+            return Ok(false);
+        }
+
+        let blind = sqlx::query_scalar!(
+            r#"
+            SELECT blind_judging FROM contests WHERE id = $1;
+            "#,
+            *contest
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(blind)
+    }
+
+    pub async fn update(id: ItemId, fields: &EntryFields) -> api::Result<Self> {
+        let contest = Self::load(id).await?.contest;
+        EntrySchema::load(contest).await?.check(&fields.custom_fields)?;
+        EmailVerification::ensure_entrant_allowed(contest, &fields.creator).await?;
+
+        let out = sqlx::query_as!(
+            Entry,
+            r#"
+            UPDATE entries SET name = $2, creator = $3, url = $4, description = $5, custom_fields = $6 WHERE id = $1
+            RETURNING id as "id: _", contest as "contest: _", name, creator, url, description, custom_fields;
+            "#,
+            *id,
+            fields.name,
+            fields.creator,
+            fields.url,
+            fields.description,
+            fields.custom_fields
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn delete(id: ItemId) -> db::Result<()> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM entries WHERE id = $1;
+            "#,
+            *id
+        ).execute(db::pool())
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl GuardedResource for Entry {
+    type ResourceId = ItemId;
+
+    async fn access_level(user: &Info, rid: &Self::ResourceId) -> api::Result<secure::Role> {
+        let role: Option<secure::Role> = sqlx::query_scalar!(
+            r#"
+            SELECT role AS "role: secure::Role" FROM entry_access_for_user($1, $2);
+            "#,
+            &user.username,
+            rid.as_ref()
+        ).fetch_one(db::pool())
+            .await?;
+
+        let role = role.ok_or(Status::NotFound)?;
+
+        Ok(role)
+    }
+
+    async fn anonymous_access_level(rid: &Self::ResourceId) -> api::Result<secure::Role> {
+        let role: Option<secure::Role> = sqlx::query_scalar!(
+            r#"
+            SELECT role AS "role: secure::Role" FROM entry_access_for_anonymous($1);
+            "#,
+            rid.as_ref()
+        ).fetch_one(db::pool())
+            .await?;
+
+        let role = role.ok_or(Status::NotFound)?;
+
+        Ok(role)
+    }
+}
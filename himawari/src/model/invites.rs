@@ -0,0 +1,241 @@
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::{Username, Email};
+use crate::model::contests::Contest;
+use crate::model::assignments::Assignment;
+use crate::model::verification::EmailVerification;
+use crate::secure::{self, SecretToken};
+use crate::{db, api, mail};
+use rocket::http::Status;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("This invite has expired.")]
+    Expired,
+    #[error("This invite has been revoked.")]
+    Revoked,
+    #[error("This invite has already been used.")]
+    AlreadyUsed,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::Expired |
+            Error::Revoked => Status::Gone,
+            Error::AlreadyUsed => Status::Conflict,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "invite_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum InviteStatus {
+    Pending,
+    Accepted,
+    Revoked,
+    Expired,
+}
+
+/// An invitation to judge a contest. The token itself is only ever shown to the owner when the
+/// invite is created.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Invite {
+    pub id: ItemId,
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub email: Option<String>,
+    #[serde(rename = "createdBy")]
+    pub created_by: Username,
+    pub created: chrono::DateTime<Utc>,
+    pub expires: chrono::DateTime<Utc>,
+    pub status: InviteStatus,
+    #[serde(rename = "acceptedBy")]
+    pub accepted_by: Option<Username>,
+    pub accepted: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Clone, Debug, validator::Validate)]
+pub struct NewInvite {
+    /// If given, the invite link is emailed here.
+    #[serde(default)]
+    pub email: Option<Email>,
+    #[serde(rename = "expiresInDays", default = "default_expiry_days")]
+    #[validate(range(min = 1, max = 30))]
+    pub expires_in_days: u32,
+}
+
+fn default_expiry_days() -> u32 {
+    7
+}
+
+/// A newly created invite along with the secret needed to accept it.
+#[derive(Serialize, Clone, Debug)]
+pub struct CreatedInvite {
+    #[serde(flatten)]
+    pub invite: Invite,
+    pub token: String,
+    pub link: String,
+}
+
+impl Invite {
+    pub async fn for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Invite,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", email::TEXT, created_by as "created_by: _", created, expires,
+                   invite_status(I) as "status!: _", accepted_by as "accepted_by: _", accepted
+            FROM judge_invites I
+            WHERE contest = $1
+            ORDER BY id DESC;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Creates an invite to `contest`, emailing the link if an address was given.
+    pub async fn create(contest: &Contest, created_by: &Username, invite: &NewInvite) -> api::Result<CreatedInvite> {
+        let token = SecretToken::generate();
+        let email = invite.email.as_ref().map(|e| e.to_string());
+
+        let out = sqlx::query_as!(
+            Invite,
+            r#"
+            INSERT INTO judge_invites (contest, token_hash, email, created_by, expires)
+            VALUES ($1, $2, $3::TEXT::CITEXT, $4, now() + make_interval(days => $5))
+            RETURNING id as "id: _", contest as "contest: _", email::TEXT, created_by as "created_by: _", created, expires,
+                      'pending'::invite_status as "status!: _", accepted_by as "accepted_by: _", accepted;
+            "#,
+            *contest.id,
+            token.hash(),
+            email,
+            created_by.as_str(),
+            invite.expires_in_days as i32
+        ).fetch_one(db::pool())
+            .await?;
+
+        let link = mail::link(&format!("invite/{}", token.expose()));
+
+        // Only keep the invite if the email actually went out.
+        if let Some(email) = &email {
+            let body = format!(
+                "{} has invited you to judge \"{}\" on Himawari.\n\n\
+                 Accept the invitation here: {}\n\n\
+                 This link expires on {}.\n",
+                created_by,
+                contest.name,
+                link,
+                out.expires.format("%Y-%m-%d %H:%M UTC")
+            );
+            if let Err(e) = mail::send(email, &format!("Invitation to judge {}", contest.name), body).await {
+                sqlx::query!(
+                    r#"
+                    DELETE FROM judge_invites WHERE id = $1;
+                    "#,
+                    *out.id
+                ).execute(db::pool())
+                    .await?;
+                return Err(e);
+            }
+        }
+
+        info!(contest = %contest.id, invite = %out.id, emailed = email.is_some(), "created judge invite");
+
+        Ok(CreatedInvite {
+            invite: out,
+            token: token.expose().to_string(),
+            link,
+        })
+    }
+
+    /// Stops a pending invite from being used.
+    pub async fn revoke(contest: ItemId, id: ItemId) -> api::Result<()> {
+        let mut tx = db::pool().begin().await?;
+        let status = sqlx::query_scalar!(
+            r#"
+            SELECT invite_status(I) as "status!: InviteStatus" FROM judge_invites I WHERE id = $1 AND contest = $2 FOR UPDATE;
+            "#,
+            *id,
+            *contest
+        ).fetch_one(&mut tx)
+            .await?;
+
+        match status {
+            InviteStatus::Accepted => return Err(Error::AlreadyUsed.into()),
+            InviteStatus::Revoked => return Ok(()),
+            InviteStatus::Pending | InviteStatus::Expired => {}
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE judge_invites SET revoked = now() WHERE id = $1;
+            "#,
+            *id
+        ).execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Uses up the invite with the given token, making `user` a judge of its contest.
+    pub async fn accept(token: &str, user: &Username) -> api::Result<Contest> {
+        let mut tx = db::pool().begin().await?;
+        let invite = sqlx::query!(
+            r#"
+            SELECT id as "id: ItemId", contest as "contest: ItemId", invite_status(I) as "status!: InviteStatus"
+            FROM judge_invites I
+            WHERE token_hash = $1 AND contest IN (SELECT id FROM contests WHERE deleted IS NULL)
+            FOR UPDATE;
+            "#,
+            secure::hash_token(token)
+        ).fetch_one(&mut tx)
+            .await?;
+
+        match invite.status {
+            InviteStatus::Pending => {}
+            InviteStatus::Accepted => return Err(Error::AlreadyUsed.into()),
+            InviteStatus::Revoked => return Err(Error::Revoked.into()),
+            InviteStatus::Expired => return Err(Error::Expired.into()),
+        }
+        EmailVerification::ensure_allowed(invite.contest, user).await?;
+
+        // Accepting an invite to a contest you already judge (or own) still uses it up.
+        sqlx::query!(
+            r#"
+            INSERT INTO contest_judges (contest, judge)
+            SELECT C.id, $2::VARCHAR FROM contests C WHERE C.id = $1 AND C.owner <> $2
+            ON CONFLICT DO NOTHING;
+            "#,
+            *invite.contest,
+            user.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE judge_invites SET accepted_by = $2, accepted = now() WHERE id = $1;
+            "#,
+            *invite.id,
+            user.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        info!(contest = %invite.contest, invite = %invite.id, judge = %user, "accepted judge invite");
+
+        let contest = Contest::load(invite.contest).await?;
+        if Assignment::enabled(contest.id).await? {
+            Assignment::rebalance(&contest).await?;
+        }
+        Ok(contest)
+    }
+}
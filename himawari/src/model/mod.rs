@@ -5,6 +5,19 @@ use rocket::http::Status;
 
 pub mod users;
 pub mod contests;
+pub mod entries;
+pub mod scores;
+pub mod results;
+pub mod ballots;
+pub mod comparisons;
+pub mod assignments;
+pub mod conflicts;
+pub mod invites;
+pub mod transfers;
+pub mod archive;
+pub mod tokens;
+pub mod verification;
+pub mod resets;
 
 pub type RawItemId = i64;
 
@@ -0,0 +1,187 @@
+//! Resetting a forgotten password by email. Reset tokens are random and only stored hashed; they
+//! expire quickly and work once. Asking for one never reveals whether the address has an account.
+
+use crate::model::ItemId;
+use crate::model::users::{Email, Username};
+use crate::model::tokens::{TokenFamily, RevocationReason};
+use crate::secure::{self, SecretToken};
+use crate::{db, api, mail};
+use rocket::http::Status;
+use std::borrow::Cow;
+
+/// How long a reset link works for.
+pub const RESET_MINUTES: i32 = 30;
+/// Further requests for the same account within this long are quietly ignored.
+const REQUEST_COOLDOWN_SECONDS: f64 = 60.0;
+/// How long used and expired resets are kept around for.
+const RETENTION_DAYS: i32 = 7;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("That reset link is invalid, has expired or has already been used.")]
+    Invalid,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::Invalid => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+pub struct PasswordReset;
+
+impl PasswordReset {
+    /// Emails a reset link to whoever `email` belongs to, if anyone. Links sent before stop working.
+    pub async fn request(email: &Email) -> api::Result<()> {
+        let mut tx = db::pool().begin().await?;
+        let user = sqlx::query!(
+            r#"
+            SELECT username as "username: Username", display_name FROM users WHERE email = $1::TEXT::CITEXT FOR UPDATE;
+            "#,
+            email.to_string()
+        ).fetch_optional(&mut tx)
+            .await?;
+
+        let user = match user {
+            Some(u) => u,
+            None => {
+                info!("password reset requested for an unknown address");
+                return Ok(());
+            }
+        };
+
+        let recent = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM password_resets
+                           WHERE username = $1 AND created > now() - make_interval(secs => $2)) as "recent!";
+            "#,
+            user.username.as_str(),
+            REQUEST_COOLDOWN_SECONDS
+        ).fetch_one(&mut tx)
+            .await?;
+        if recent {
+            info!(user = %user.username, "ignoring repeated password reset request");
+            return Ok(());
+        }
+
+        Self::cancel_pending(&user.username, &mut tx).await?;
+
+        let token = SecretToken::generate();
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO password_resets (token_hash, username, expires) VALUES ($1, $2, now() + make_interval(mins => $3))
+            RETURNING id as "id: ItemId";
+            "#,
+            token.hash(),
+            user.username.as_str(),
+            RESET_MINUTES
+        ).fetch_one(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        let body = format!(
+            "Hi {},\n\n\
+             Someone asked to reset the password for your Himawari account, {}. \
+             If it was you, choose a new password here: {}\n\n\
+             The link expires in {} minutes. If you didn't ask for this, you can ignore this email; \
+             your password hasn't been changed.\n",
+            user.display_name,
+            user.username,
+            mail::link(&format!("reset-password/{}", token.expose())),
+            RESET_MINUTES
+        );
+        // Sent outside the transaction so a slow mail server doesn't hold the user's row, but the
+        // reset is only kept if the email actually went out.
+        if let Err(e) = mail::send(&email.to_string(), "Reset your password", body).await {
+            sqlx::query!(
+                r#"
+                DELETE FROM password_resets WHERE id = $1;
+                "#,
+                *id
+            ).execute(db::pool())
+                .await?;
+            return Err(e);
+        }
+
+        info!(user = %user.username, "sent password reset email");
+        Ok(())
+    }
+
+    /// Stops any reset links the user has been sent from working, as part of a larger change.
+    pub async fn cancel_pending(user: &Username, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> db::Result<u64> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM password_resets WHERE username = $1 AND used IS NULL;
+            "#,
+            user.as_str()
+        ).execute(&mut *tx)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// Fails unless `token` could be used to reset a password right now. Worth checking before
+    /// hashing the new password, so bad tokens can't keep the hasher busy.
+    pub async fn ensure_usable(token: &str) -> api::Result<()> {
+        let usable = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (SELECT 1 FROM password_resets
+                           WHERE token_hash = $1 AND used IS NULL AND expires > now()) as "usable!";
+            "#,
+            secure::hash_token(token)
+        ).fetch_one(db::pool())
+            .await?;
+
+        if usable {
+            Ok(())
+        } else {
+            Err(Error::Invalid.into())
+        }
+    }
+
+    /// Uses up a reset token, giving its user the password `hash` is of and logging them out
+    /// everywhere.
+    pub async fn complete(token: &str, hash: String) -> api::Result<Username> {
+        let mut tx = db::pool().begin().await?;
+        let user = sqlx::query_scalar!(
+            r#"
+            UPDATE password_resets SET used = now()
+            WHERE token_hash = $1 AND used IS NULL AND expires > now()
+            RETURNING username as "username: Username";
+            "#,
+            secure::hash_token(token)
+        ).fetch_optional(&mut tx)
+            .await?
+            .ok_or(Error::Invalid)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE users SET hash = $2 WHERE username = $1;
+            "#,
+            user.as_str(),
+            hash
+        ).execute(&mut tx)
+            .await?;
+
+        let sessions = TokenFamily::revoke_all(&user, RevocationReason::PasswordReset, &mut tx).await?;
+        tx.commit().await?;
+        info!(user = %user, sessions, "reset password");
+        Ok(user)
+    }
+
+    pub async fn purge_expired() -> db::Result<u64> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM password_resets WHERE expires < now() - make_interval(days => $1);
+            "#,
+            RETENTION_DAYS
+        ).execute(db::pool())
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
\ No newline at end of file
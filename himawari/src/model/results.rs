@@ -0,0 +1,162 @@
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::{Contest, JudgingMode};
+use crate::model::scores::{Criterion, Score};
+use crate::model::ballots::Ballot;
+use crate::model::comparisons::Comparison;
+use crate::model::conflicts::Conflict;
+use crate::tally::aggregate::{self, Method, EntryResult};
+use crate::tally::ranked::{self, Tally};
+use crate::tally::pairwise::{self, Fit};
+use crate::{db, api};
+use validator::Validate;
+
+/// How a contest is judged and how judgments become a ranking.
+#[derive(Clone, Debug, Serialize, Deserialize, Validate)]
+pub struct ScoringSettings {
+    #[serde(default = "default_mode")]
+    pub mode: JudgingMode,
+    pub method: Method,
+    /// Fraction of scores dropped from each end when `method` is `trimmed_mean`.
+    #[serde(rename = "trimFraction")]
+    #[validate(range(min = 0.0, max = 0.49))]
+    pub trim_fraction: f64,
+    /// Tally used when `mode` is `ballots`.
+    #[serde(rename = "ballotMethod", default = "default_ballot_method")]
+    pub ballot_method: ranked::Method,
+    /// Rating model used when `mode` is `pairwise`.
+    #[serde(rename = "pairwiseMethod", default = "default_pairwise_method")]
+    pub pairwise_method: pairwise::Method,
+    /// Hides entry creators, urls and identifying custom fields from judges who aren't the owner.
+    /// Required rather than defaulted, since saving settings replaces all of them and leaving it
+    /// out shouldn't quietly unblind a contest.
+    #[serde(rename = "blindJudging")]
+    pub blind_judging: bool,
+}
+
+fn default_mode() -> JudgingMode {
+    JudgingMode::Scores
+}
+
+fn default_ballot_method() -> ranked::Method {
+    ranked::Method::Borda
+}
+
+fn default_pairwise_method() -> pairwise::Method {
+    pairwise::Method::BradleyTerry
+}
+
+impl ScoringSettings {
+    pub async fn load(contest: ItemId) -> db::Result<Self> {
+        let out = sqlx::query_as!(
+            ScoringSettings,
+            r#"
+            SELECT judging_mode as "mode: _", aggregation as "method: _", trim_fraction, ballot_method as "ballot_method: _",
+                   pairwise_method as "pairwise_method: _", blind_judging
+            FROM contests
+            WHERE id = $1;
+            "#,
+            *contest
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Replaces the contest's settings, as long as it's still at `version`. Returns the new version,
+    /// or `None` if someone else changed the contest first.
+    pub async fn save(&self, contest: ItemId, version: i64) -> db::Result<Option<i64>> {
+        let out = sqlx::query_scalar!(
+            r#"
+            UPDATE contests
+            SET judging_mode = $2, aggregation = $3, trim_fraction = $4, ballot_method = $5, pairwise_method = $6,
+                blind_judging = $7
+            WHERE id = $1 AND version = $8
+            RETURNING version;
+            "#,
+            *contest,
+            self.mode as JudgingMode,
+            self.method as Method,
+            self.trim_fraction,
+            self.ballot_method as ranked::Method,
+            self.pairwise_method as pairwise::Method,
+            self.blind_judging,
+            version
+        ).fetch_optional(db::pool())
+            .await?;
+
+        if out.is_none() {
+            // Tells a missing contest apart from a stale version.
+            Contest::load(contest).await?;
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Outcome {
+    Scores {
+        entries: Vec<EntryResult>,
+    },
+    Ballots(Tally),
+    Pairwise(Fit),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Results {
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub settings: ScoringSettings,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl Results {
+    pub async fn compute(contest: &Contest) -> api::Result<Self> {
+        let settings = ScoringSettings::load(contest.id).await?;
+        let judges = contest.judges().await?;
+        let entries = contest.entries().await?;
+        // Judgments a judge made before declaring a conflict don't count.
+        let excluded = Conflict::excluded_pairs(contest.id).await?;
+        let counts = |entry: &ItemId, judge: &Username| !excluded.contains(&(*entry, judge.clone()));
+
+        let outcome = match settings.mode {
+            JudgingMode::Scores => {
+                let rubric = Criterion::for_contest(contest.id).await?;
+                let scores: Vec<Score> = Score::all_for_contest(contest.id).await?
+                    .into_iter()
+                    .filter(|s| counts(&s.entry, &s.judge))
+                    .collect();
+
+                let totals = aggregate::judge_totals(&rubric, &scores);
+                let ranked = aggregate::aggregate(settings.method, settings.trim_fraction, &totals, &judges, &entries);
+                Outcome::Scores { entries: ranked }
+            }
+            JudgingMode::Ballots => {
+                let ballots: Vec<Vec<ItemId>> = Ballot::all_for_contest(contest.id).await?
+                    .into_iter()
+                    .filter(|b| judges.contains(&b.judge))
+                    .map(|b| {
+                        let judge = b.judge;
+                        b.ranking.into_iter().filter(|e| counts(e, &judge)).collect()
+                    })
+                    .collect();
+                Outcome::Ballots(ranked::tally(settings.ballot_method, &entries, &ballots))
+            }
+            JudgingMode::Pairwise => {
+                let results: Vec<(ItemId, ItemId)> = Comparison::all_for_contest(contest.id).await?
+                    .into_iter()
+                    .filter(|c| judges.contains(&c.judge) && counts(&c.winner, &c.judge) && counts(&c.loser, &c.judge))
+                    .map(|c| (c.winner, c.loser))
+                    .collect();
+                Outcome::Pairwise(pairwise::fit(settings.pairwise_method, &entries, &results))
+            }
+        };
+
+        Ok(Self {
+            contest: contest.id,
+            settings,
+            outcome,
+        })
+    }
+}
@@ -0,0 +1,316 @@
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::{Contest, JudgingMode, Phase};
+use crate::{db, api};
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("This contest isn't open for judging.")]
+    NotJudging,
+    #[error("This contest is not judged by rubric scores.")]
+    WrongMode,
+    #[error("Criterion {0} is not part of this contest's rubric.")]
+    UnknownCriterion(ItemId),
+    #[error("Score for criterion {0} must be between {1} and {2}.")]
+    OutOfRange(ItemId, f64, f64),
+    #[error("The rubric can't be changed once judging has started.")]
+    RubricLocked,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::NotJudging |
+            Error::WrongMode |
+            Error::RubricLocked => Status::Conflict,
+            Error::UnknownCriterion(_) |
+            Error::OutOfRange(..) => Status::BadRequest,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+/// One line of a contest's scoring rubric.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Criterion {
+    pub id: ItemId,
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "minScore")]
+    pub min_score: f64,
+    #[serde(rename = "maxScore")]
+    pub max_score: f64,
+    pub weight: f64,
+}
+
+#[derive(Deserialize, Clone, Debug, Validate)]
+#[validate(schema(function = "validate_bounds"))]
+pub struct CriterionFields {
+    #[validate(length(min = 1, max = 1024), non_control_character)]
+    pub name: String,
+    #[validate(length(max = 65535))]
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(rename = "minScore")]
+    pub min_score: f64,
+    #[serde(rename = "maxScore")]
+    pub max_score: f64,
+    #[validate(range(min = 0.0))]
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn validate_bounds(fields: &CriterionFields) -> Result<(), ValidationError> {
+    if fields.min_score.is_finite() && fields.max_score.is_finite() && fields.min_score < fields.max_score {
+        Ok(())
+    } else {
+        Err(ValidationError::new("minScore must be less than maxScore"))
+    }
+}
+
+impl Criterion {
+    /// Checks that `score` is within the criterion's range.
+    pub fn check(&self, score: f64) -> Result<(), Error> {
+        if (self.min_score..=self.max_score).contains(&score) {
+            Ok(())
+        } else {
+            Err(Error::OutOfRange(self.id, self.min_score, self.max_score))
+        }
+    }
+
+    pub async fn for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Criterion,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", name, description, min_score, max_score, weight
+            FROM rubric_criteria
+            WHERE contest = $1
+            ORDER BY id;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Holds the contest's phase for the rest of `tx`, failing if judges may already have scored
+    /// against the rubric. Changing it after that would leave scores for criteria that no longer
+    /// exist or out of their range.
+    async fn ensure_editable(contest: ItemId, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> api::Result<()> {
+        let state = Contest::lock_state(contest, tx).await?;
+        if state.phase >= Phase::Judging {
+            return Err(Error::RubricLocked.into());
+        }
+        Ok(())
+    }
+
+    pub async fn create(contest: ItemId, fields: &CriterionFields) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        Self::ensure_editable(contest, &mut tx).await?;
+
+        let out = sqlx::query_as!(
+            Criterion,
+            r#"
+            INSERT INTO rubric_criteria (contest, name, description, min_score, max_score, weight)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id as "id: _", contest as "contest: _", name, description, min_score, max_score, weight;
+            "#,
+            *contest,
+            fields.name,
+            fields.description,
+            fields.min_score,
+            fields.max_score,
+            fields.weight
+        ).fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(out)
+    }
+
+    pub async fn update(contest: ItemId, id: ItemId, fields: &CriterionFields) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        Self::ensure_editable(contest, &mut tx).await?;
+
+        let out = sqlx::query_as!(
+            Criterion,
+            r#"
+            UPDATE rubric_criteria
+            SET name = $3, description = $4, min_score = $5, max_score = $6, weight = $7
+            WHERE contest = $1 AND id = $2
+            RETURNING id as "id: _", contest as "contest: _", name, description, min_score, max_score, weight;
+            "#,
+            *contest,
+            *id,
+            fields.name,
+            fields.description,
+            fields.min_score,
+            fields.max_score,
+            fields.weight
+        ).fetch_one(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(out)
+    }
+
+    pub async fn delete(contest: ItemId, id: ItemId) -> api::Result<()> {
+        let mut tx = db::pool().begin().await?;
+        Self::ensure_editable(contest, &mut tx).await?;
+
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM rubric_criteria WHERE contest = $1 AND id = $2;
+            "#,
+            *contest,
+            *id
+        ).execute(&mut tx)
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound.into());
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// A single judge's score for one entry against one criterion.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Score {
+    #[serde(rename = "entryId")]
+    pub entry: ItemId,
+    #[serde(rename = "criterionId")]
+    pub criterion: ItemId,
+    pub judge: Username,
+    pub score: f64,
+    pub updated: chrono::DateTime<Utc>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScoreSubmission {
+    #[serde(rename = "criterionId")]
+    pub criterion: ItemId,
+    pub score: f64,
+}
+
+impl Score {
+    pub async fn for_entry(entry: ItemId, judge: &Username) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Score,
+            r#"
+            SELECT entry as "entry: _", criterion as "criterion: _", judge as "judge: _", score, updated
+            FROM scores
+            WHERE entry = $1 AND judge = $2
+            ORDER BY criterion;
+            "#,
+            *entry,
+            judge.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    pub async fn for_contest(contest: ItemId, judge: &Username) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Score,
+            r#"
+            SELECT S.entry as "entry: _", S.criterion as "criterion: _", S.judge as "judge: _", S.score, S.updated
+            FROM scores S JOIN entries E ON E.id = S.entry
+            WHERE E.contest = $1 AND S.judge = $2
+            ORDER BY S.entry, S.criterion;
+            "#,
+            *contest,
+            judge.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Every judge's scores for every entry in the contest.
+    pub async fn all_for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Score,
+            r#"
+            SELECT S.entry as "entry: _", S.criterion as "criterion: _", S.judge as "judge: _", S.score, S.updated
+            FROM scores S JOIN entries E ON E.id = S.entry
+            WHERE E.contest = $1
+            ORDER BY S.entry, S.criterion, S.judge;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Records (or revises) a judge's scores for an entry, checking each one against the contest's rubric.
+    pub async fn submit(entry: ItemId, judge: &Username, submissions: &[ScoreSubmission]) -> api::Result<Vec<Self>> {
+        let mut tx = db::pool().begin().await?;
+
+        let contest = sqlx::query_scalar!(
+            r#"
+            SELECT contest as "contest: ItemId" FROM entries WHERE id = $1;
+            "#,
+            *entry
+        ).fetch_one(&mut tx)
+            .await?;
+
+        let state = Contest::lock_state(contest, &mut tx).await?;
+        if state.phase != Phase::Judging {
+            return Err(Error::NotJudging.into());
+        }
+        if state.mode != JudgingMode::Scores {
+            return Err(Error::WrongMode.into());
+        }
+
+        let rubric: BTreeMap<ItemId, Criterion> = sqlx::query_as!(
+            Criterion,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", name, description, min_score, max_score, weight
+            FROM rubric_criteria
+            WHERE contest = $1;
+            "#,
+            *contest
+        ).fetch_all(&mut tx)
+            .await?
+            .into_iter()
+            .map(|c| (c.id, c))
+            .collect();
+
+        for submission in submissions {
+            rubric.get(&submission.criterion)
+                .ok_or(Error::UnknownCriterion(submission.criterion))?
+                .check(submission.score)?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO scores (entry, criterion, judge, score) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (entry, criterion, judge) DO UPDATE SET score = EXCLUDED.score, updated = now();
+                "#,
+                *entry,
+                *submission.criterion,
+                judge.as_str(),
+                submission.score
+            ).execute(&mut tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(Self::for_entry(entry, judge).await?)
+    }
+}
@@ -0,0 +1,287 @@
+//! The server-side half of authentication. Access tokens are short-lived JWTs naming the token
+//! family they came from; a family is revoked by logging out, and every access token from it stops
+//! working at once. Refresh tokens are single use: exchanging one issues its replacement, and
+//! presenting one a second time means it was copied, so the whole family is revoked.
+//!
+//! Users see each family as a session: one login on one device.
+
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::secure::{self, SecretToken};
+use crate::http::ClientInfo;
+use crate::{db, api};
+use rocket::http::Status;
+use std::borrow::Cow;
+
+/// How long an access token is accepted for.
+pub const ACCESS_TOKEN_MINUTES: u64 = 15;
+/// How long a refresh token can go unused before it expires.
+pub const REFRESH_TOKEN_DAYS: i32 = 30;
+/// How long a family is kept after its last refresh token expired, so reuse is still recognised.
+const RETENTION_DAYS: i32 = 7;
+/// A session's last use is only recorded this often, so most requests don't write.
+const LAST_USED_RESOLUTION_SECONDS: f64 = 60.0;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("That refresh token is invalid or has expired.")]
+    Invalid,
+    #[error("That refresh token has already been used. You've been logged out everywhere it was shared.")]
+    Reused,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::Invalid |
+            Error::Reused => Status::Unauthorized,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "revocation_reason", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationReason {
+    Logout,
+    /// A refresh token was presented after it had been exchanged.
+    Reuse,
+    /// Ended by its user from another session.
+    Remote,
+    /// The user's password was reset.
+    PasswordReset,
+}
+
+serde_plain::forward_display_to_serde!(RevocationReason);
+
+/// The tokens descended from one login.
+pub struct TokenFamily;
+
+/// A login that can still be used.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub id: ItemId,
+    pub created: chrono::DateTime<Utc>,
+    #[serde(rename = "lastUsed")]
+    pub last_used: chrono::DateTime<Utc>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether this is the session the request was made with.
+    pub current: bool,
+}
+
+impl TokenFamily {
+    /// Starts a family for a fresh login and returns it with its first refresh token.
+    pub async fn start(user: &Username, client: &ClientInfo) -> db::Result<(ItemId, SecretToken)> {
+        let mut tx = db::pool().begin().await?;
+        let family = sqlx::query_scalar!(
+            r#"
+            INSERT INTO token_families (username, user_agent, ip) VALUES ($1, $2, $3) RETURNING id as "id: ItemId";
+            "#,
+            user.as_str(),
+            client.user_agent,
+            client.ip
+        ).fetch_one(&mut tx)
+            .await?;
+
+        let refresh = Self::issue(family, &mut tx).await?;
+        tx.commit().await?;
+        Ok((family, refresh))
+    }
+
+    async fn issue(family: ItemId, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> db::Result<SecretToken> {
+        let refresh = SecretToken::generate();
+        sqlx::query!(
+            r#"
+            INSERT INTO refresh_tokens (token_hash, family, expires) VALUES ($1, $2, now() + make_interval(days => $3));
+            "#,
+            refresh.hash(),
+            *family,
+            REFRESH_TOKEN_DAYS
+        ).execute(&mut *tx)
+            .await?;
+        Ok(refresh)
+    }
+
+    /// Exchanges a refresh token for its replacement, returning the family and who it belongs to.
+    pub async fn rotate(presented: &str, client: &ClientInfo) -> api::Result<(ItemId, Username, SecretToken)> {
+        let hash = secure::hash_token(presented);
+        let mut tx = db::pool().begin().await?;
+
+        let current = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens R SET used = now()
+            FROM token_families F
+            WHERE R.token_hash = $1 AND R.used IS NULL AND R.expires > now()
+              AND F.id = R.family AND F.revoked IS NULL
+            RETURNING R.family as "family: ItemId", F.username as "username: Username";
+            "#,
+            hash
+        ).fetch_optional(&mut tx)
+            .await?;
+
+        let current = match current {
+            Some(c) => c,
+            None => {
+                tx.rollback().await?;
+                return Err(Self::reject(&hash).await?.into());
+            }
+        };
+
+        sqlx::query!(
+            r#"
+            UPDATE token_families SET last_used = now(), user_agent = COALESCE($2, user_agent), ip = COALESCE($3, ip)
+            WHERE id = $1;
+            "#,
+            *current.family,
+            client.user_agent,
+            client.ip
+        ).execute(&mut tx)
+            .await?;
+
+        let refresh = Self::issue(current.family, &mut tx).await?;
+        tx.commit().await?;
+        Ok((current.family, current.username, refresh))
+    }
+
+    /// Works out why a refresh token wasn't accepted, revoking its family if it had been used.
+    async fn reject(hash: &[u8]) -> db::Result<Error> {
+        let used = sqlx::query!(
+            r#"
+            SELECT family as "family: ItemId", used IS NOT NULL as "used!" FROM refresh_tokens WHERE token_hash = $1;
+            "#,
+            hash
+        ).fetch_optional(db::pool())
+            .await?;
+
+        match used {
+            Some(t) if t.used => {
+                warn!(family = %t.family, "refresh token reused; revoking its family");
+                Self::revoke(t.family, RevocationReason::Reuse).await?;
+                Ok(Error::Reused)
+            }
+            _ => Ok(Error::Invalid),
+        }
+    }
+
+    /// Revokes the family, if it isn't already.
+    pub async fn revoke(family: ItemId, reason: RevocationReason) -> db::Result<()> {
+        sqlx::query!(
+            r#"
+            UPDATE token_families SET revoked = now(), revoked_reason = $2 WHERE id = $1 AND revoked IS NULL;
+            "#,
+            *family,
+            reason as RevocationReason
+        ).execute(db::pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Whether access tokens from the family should still be accepted. Records that the family
+    /// was used if it hasn't been for a while.
+    pub async fn touch(family: ItemId) -> db::Result<bool> {
+        let active = sqlx::query_scalar!(
+            r#"
+            WITH active AS (
+                SELECT id, last_used FROM token_families WHERE id = $1 AND revoked IS NULL
+            ), touched AS (
+                UPDATE token_families F SET last_used = now()
+                FROM active A
+                WHERE F.id = A.id AND A.last_used < now() - make_interval(secs => $2)
+            )
+            SELECT EXISTS (SELECT 1 FROM active) as "active!";
+            "#,
+            *family,
+            LAST_USED_RESOLUTION_SECONDS
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(active)
+    }
+
+    /// The user's sessions that haven't been revoked or expired, most recently used first.
+    pub async fn sessions(user: &Username, current: ItemId) -> db::Result<Vec<Session>> {
+        let out = sqlx::query_as!(
+            Session,
+            r#"
+            SELECT F.id as "id: _", F.created, F.last_used, F.user_agent, F.ip, F.id = $2 as "current!"
+            FROM token_families F
+            WHERE F.username = $1 AND F.revoked IS NULL
+              AND EXISTS (SELECT 1 FROM refresh_tokens R WHERE R.family = F.id AND R.used IS NULL AND R.expires > now())
+            ORDER BY F.last_used DESC, F.id DESC;
+            "#,
+            user.as_str(),
+            *current
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Revokes one of the user's sessions.
+    pub async fn revoke_session(user: &Username, family: ItemId) -> db::Result<()> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE token_families SET revoked = now(), revoked_reason = 'remote'
+            WHERE id = $1 AND username = $2 AND revoked IS NULL;
+            "#,
+            *family,
+            user.as_str()
+        ).execute(db::pool())
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Revokes every one of the user's sessions except `keep`, returning how many there were.
+    pub async fn revoke_others(user: &Username, keep: ItemId, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> db::Result<u64> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE token_families SET revoked = now(), revoked_reason = 'remote'
+            WHERE username = $1 AND id <> $2 AND revoked IS NULL;
+            "#,
+            user.as_str(),
+            *keep
+        ).execute(&mut *tx)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// Revokes every one of the user's sessions as part of a larger change.
+    pub async fn revoke_all(user: &Username, reason: RevocationReason, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> db::Result<u64> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE token_families SET revoked = now(), revoked_reason = $2
+            WHERE username = $1 AND revoked IS NULL;
+            "#,
+            user.as_str(),
+            reason as RevocationReason
+        ).execute(&mut *tx)
+            .await?;
+        Ok(res.rows_affected())
+    }
+
+    /// Deletes families that can no longer be used, once they're old enough that nobody will
+    /// present their tokens again.
+    pub async fn purge_expired() -> db::Result<u64> {
+        let res = sqlx::query!(
+            r#"
+            DELETE FROM token_families F
+            WHERE F.created < now() - make_interval(days => $1)
+              AND NOT EXISTS (SELECT 1 FROM refresh_tokens R
+                              WHERE R.family = F.id AND R.expires > now() - make_interval(days => $1));
+            "#,
+            RETENTION_DAYS
+        ).execute(db::pool())
+            .await?;
+        Ok(res.rows_affected())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,255 @@
+use chrono::Utc;
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::contests::Contest;
+use crate::{db, api};
+use rocket::http::Status;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("You already own this contest.")]
+    AlreadyOwner,
+    #[error("This transfer was proposed to someone else.")]
+    NotRecipient,
+    #[error("The contest changed owners since this transfer was proposed.")]
+    Stale,
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::AlreadyOwner => Status::BadRequest,
+            Error::NotRecipient => Status::Forbidden,
+            Error::Stale => Status::Conflict,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "transfer_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Cancelled,
+}
+
+/// A proposal to hand a contest to a new owner, and what became of it.
+#[derive(sqlx::FromRow, Clone, Debug, Serialize, Deserialize)]
+pub struct Transfer {
+    pub id: ItemId,
+    #[serde(rename = "contestId")]
+    pub contest: ItemId,
+    #[serde(rename = "fromOwner")]
+    pub from_owner: Username,
+    #[serde(rename = "toOwner")]
+    pub to_owner: Username,
+    pub status: TransferStatus,
+    pub proposed: chrono::DateTime<Utc>,
+    pub resolved: Option<chrono::DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TransferProposal {
+    #[serde(rename = "newOwner")]
+    pub new_owner: Username,
+}
+
+impl Transfer {
+    /// Every transfer ever proposed for the contest, newest first.
+    pub async fn for_contest(contest: ItemId) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Transfer,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", from_owner as "from_owner: _", to_owner as "to_owner: _",
+                   status as "status: _", proposed, resolved
+            FROM ownership_transfers
+            WHERE contest = $1
+            ORDER BY id DESC;
+            "#,
+            *contest
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Open proposals waiting for `user` to accept or decline.
+    pub async fn incoming(user: &Username) -> db::Result<Vec<Self>> {
+        let out = sqlx::query_as!(
+            Transfer,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", from_owner as "from_owner: _", to_owner as "to_owner: _",
+                   status as "status: _", proposed, resolved
+            FROM ownership_transfers
+            WHERE to_owner = $1 AND status = 'pending'
+              AND contest IN (SELECT id FROM contests WHERE deleted IS NULL)
+            ORDER BY id;
+            "#,
+            user.as_str()
+        ).fetch_all(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    /// Offers the contest to `new_owner`. Only one proposal per contest can be open at a time.
+    pub async fn propose(contest: &Contest, new_owner: &Username) -> api::Result<Self> {
+        let mut tx = db::pool().begin().await?;
+        let out = Self::propose_in(contest, new_owner, &mut tx).await?;
+        tx.commit().await?;
+        Ok(out)
+    }
+
+    /// Offers the contest to `new_owner` as part of a larger change.
+    pub async fn propose_in(contest: &Contest, new_owner: &Username, tx: &mut sqlx::Transaction<'_, sqlx::Postgres>) -> api::Result<Self> {
+        if *new_owner == contest.owner {
+            return Err(Error::AlreadyOwner.into());
+        }
+
+        let out = sqlx::query_as!(
+            Transfer,
+            r#"
+            INSERT INTO ownership_transfers (contest, from_owner, to_owner)
+            SELECT $1, $2, username FROM users WHERE username = $3
+            RETURNING id as "id: _", contest as "contest: _", from_owner as "from_owner: _", to_owner as "to_owner: _",
+                      status as "status: _", proposed, resolved;
+            "#,
+            *contest.id,
+            contest.owner.as_str(),
+            new_owner.as_str()
+        ).fetch_one(&mut *tx)
+            .await?;
+
+        info!(contest = %contest.id, from = %contest.owner, to = %new_owner, "proposed ownership transfer");
+        Ok(out)
+    }
+
+    /// Withdraws the contest's open proposal.
+    pub async fn cancel(contest: ItemId) -> db::Result<()> {
+        Self::resolve_pending(contest, TransferStatus::Cancelled).await
+    }
+
+    /// Turns down the contest's open proposal, if it was made to `user`.
+    pub async fn decline(contest: ItemId, user: &Username) -> api::Result<()> {
+        let pending = Self::pending(contest).await?;
+        if pending.to_owner != *user {
+            return Err(Error::NotRecipient.into());
+        }
+        Self::resolve_pending(contest, TransferStatus::Declined).await?;
+        Ok(())
+    }
+
+    /// Makes `user` the owner of the contest if they were offered it. The previous owner stays on
+    /// as a judge. Owner and judges change in one transaction, with the contest row locked, so
+    /// access checks never see the contest with no owner or two.
+    pub async fn accept(contest: ItemId, user: &Username) -> api::Result<Contest> {
+        let mut tx = db::pool().begin().await?;
+
+        let owner = sqlx::query_scalar!(
+            r#"
+            SELECT owner as "owner: Username" FROM contests WHERE id = $1 AND deleted IS NULL FOR UPDATE;
+            "#,
+            *contest
+        ).fetch_one(&mut tx)
+            .await?;
+
+        let pending = sqlx::query_as!(
+            Transfer,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", from_owner as "from_owner: _", to_owner as "to_owner: _",
+                   status as "status: _", proposed, resolved
+            FROM ownership_transfers
+            WHERE contest = $1 AND status = 'pending'
+            FOR UPDATE;
+            "#,
+            *contest
+        ).fetch_one(&mut tx)
+            .await?;
+
+        if pending.to_owner != *user {
+            return Err(Error::NotRecipient.into());
+        }
+        if pending.from_owner != owner {
+            return Err(Error::Stale.into());
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE contests SET owner = $2 WHERE id = $1;
+            "#,
+            *contest,
+            user.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        // The owner is always a judge implicitly, so the new owner's explicit row goes and the old
+        // owner gets one.
+        sqlx::query!(
+            r#"
+            DELETE FROM contest_judges WHERE contest = $1 AND judge = $2;
+            "#,
+            *contest,
+            user.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO contest_judges (contest, judge) VALUES ($1, $2) ON CONFLICT DO NOTHING;
+            "#,
+            *contest,
+            owner.as_str()
+        ).execute(&mut tx)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE ownership_transfers SET status = 'accepted', resolved = now() WHERE id = $1;
+            "#,
+            *pending.id
+        ).execute(&mut tx)
+            .await?;
+
+        tx.commit().await?;
+        info!(contest = %contest, from = %owner, to = %user, "transferred contest ownership");
+
+        Ok(Contest::load(contest).await?)
+    }
+
+    async fn pending(contest: ItemId) -> db::Result<Self> {
+        let out = sqlx::query_as!(
+            Transfer,
+            r#"
+            SELECT id as "id: _", contest as "contest: _", from_owner as "from_owner: _", to_owner as "to_owner: _",
+                   status as "status: _", proposed, resolved
+            FROM ownership_transfers
+            WHERE contest = $1 AND status = 'pending';
+            "#,
+            *contest
+        ).fetch_one(db::pool())
+            .await?;
+        Ok(out)
+    }
+
+    async fn resolve_pending(contest: ItemId, status: TransferStatus) -> db::Result<()> {
+        let res = sqlx::query!(
+            r#"
+            UPDATE ownership_transfers SET status = $2, resolved = now() WHERE contest = $1 AND status = 'pending';
+            "#,
+            *contest,
+            status as TransferStatus
+        ).execute(db::pool())
+            .await?;
+
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+
+        Ok(())
+    }
+}
@@ -13,9 +13,12 @@ use sqlx::{
     types::chrono,
     postgres::PgRow
 };
-use crate::model::contests::Contest;
+use crate::model::contests::{Contest, ContestQuery, ContestSummary, Page, Phase, Progress, Visibility};
 use super::ItemId;
+use crate::model::tokens::TokenFamily;
+use crate::model::resets::PasswordReset;
 use crate::secure::{GuardedResource, Role};
+use rocket::request::FromParam;
 
 #[derive(Deserialize, Validate, Clone)]
 #[serde(try_from = "String")]
@@ -107,6 +110,14 @@ impl Into<String> for Username {
     }
 }
 
+impl<'r> FromParam<'r> for Username {
+    type Error = api::Error;
+
+    fn from_param(param: &'r str) -> Result<Self, Self::Error> {
+        Ok(Self::try_from(param.to_string())?)
+    }
+}
+
 impl TryFrom<String> for Username {
     type Error = ValidationErrors;
 
@@ -190,6 +201,54 @@ impl User {
                     user.as_str()
                 ).fetch_one(db::pool()).await?.try_into()?)
     }
+    pub async fn set_display_name(user: &Username, display_name: &Username) -> db::Result<()> {
+        let res = sqlx::query!(
+            r#"UPDATE users SET display_name = $2 WHERE username = $1"#,
+            user.as_str(),
+            display_name.as_str()
+        ).execute(db::pool()).await?;
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+        Ok(())
+    }
+    /// Gives the user the password `hash` is of. At the same time, every session but `keep` is
+    /// logged out and any reset links they were sent stop working. Returns how many sessions
+    /// were ended.
+    pub async fn change_password(user: &Username, hash: String, keep: ItemId) -> db::Result<u64> {
+        let mut tx = db::pool().begin().await?;
+        let res = sqlx::query!(
+            r#"UPDATE users SET hash = $2 WHERE username = $1"#,
+            user.as_str(),
+            hash
+        ).execute(&mut tx).await?;
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+
+        let sessions = TokenFamily::revoke_others(user, keep, &mut tx).await?;
+        PasswordReset::cancel_pending(user, &mut tx).await?;
+        tx.commit().await?;
+        Ok(sessions)
+    }
+    /// Changes the user's email address. A different address needs verifying again, and reset
+    /// links sent to the old one stop working.
+    pub async fn set_email(user: &Username, email: &Email) -> db::Result<()> {
+        let mut tx = db::pool().begin().await?;
+        let res = sqlx::query!(
+            r#"UPDATE users SET email = $2::TEXT::CITEXT, email_validated = email_validated AND email = $2::TEXT::CITEXT
+               WHERE username = $1"#,
+            user.as_str(),
+            email.to_string()
+        ).execute(&mut tx).await?;
+        if res.rows_affected() < 1 {
+            return Err(db::Error::NotFound);
+        }
+
+        PasswordReset::cancel_pending(user, &mut tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
     pub fn username(&self) -> &Username {
         &self.username
     }
@@ -216,9 +275,29 @@ pub struct Info {
     #[serde(rename = "displayName")]
     pub display_name: Username,
     pub email: Email,
+    #[serde(rename = "emailVerified", default)]
+    pub email_verified: bool,
     pub created: chrono::DateTime<Utc>,
 }
 
+/// Whoever is making a request: a logged-in user, or `None` for someone anonymous.
+#[derive(Clone, Debug)]
+pub struct Viewer(pub Option<Info>);
+
+impl Viewer {
+    pub fn username(&self) -> Option<&Username> {
+        self.0.as_ref().map(|i| &i.username)
+    }
+
+    /// Like [`Info::access_level`], but anonymous viewers get the resource's anonymous access.
+    pub async fn access_level<GR: GuardedResource>(&self, id: &GR::ResourceId) -> api::Result<secure::Role> {
+        match &self.0 {
+            Some(info) => info.access_level::<GR>(id).await,
+            None => GR::anonymous_access_level(id).await,
+        }
+    }
+}
+
 impl fmt::Debug for Info {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Info")
@@ -241,22 +320,117 @@ impl From<User> for Info {
             username: u.username,
             display_name: u.display_name,
             email: u.email,
+            email_verified: u.email_validated,
             created: u.created,
         }
     }
 }
 
 impl Info {
-    pub async fn contests(&self) -> api::Result<Vec<Contest>> {
-        sqlx::query_as!(
-            Contest,
+    /// A page of the contests the user owns or judges, with what the contest list shows for each.
+    pub async fn contests(&self, query: &ContestQuery) -> api::Result<Page<ContestSummary>> {
+        let after = query.after()?;
+        let (after_id, after_name) = match after {
+            Some((id, name)) => (Some(*id), name),
+            None => (None, None),
+        };
+        let relation = query.relation.to_string();
+        let pattern = query.name_pattern();
+        let sort = query.sort.to_string();
+        let limit = query.limit();
+
+        // One extra row says whether there's another page. The total is counted over every matching
+        // contest before the cursor and limit apply; the other counts are only worked out for the
+        // contests on this page.
+        let rows = sqlx::query!(
             r#"
-            SELECT id as "id!: ItemId", owner as "owner!: Username", name as "name!", created as "created!" FROM user_contests($1) ORDER BY id DESC;
+            WITH matching AS (
+                SELECT C.*, COUNT(*) OVER () as total
+                FROM user_contests($1) C
+                WHERE ($2::TEXT = 'all' OR ($2 = 'owned') = (C.owner = $1))
+                  AND ($3::contest_phase IS NULL OR C.phase = $3)
+                  AND ($4::TEXT IS NULL OR C.name ILIKE $4)
+            ), page AS (
+                SELECT C.*
+                FROM matching C
+                WHERE ($6::INT8 IS NULL OR CASE $5::TEXT
+                      WHEN 'newest' THEN C.id < $6
+                      WHEN 'oldest' THEN C.id > $6
+                      WHEN 'name' THEN (C.name, C.id) > ($7::TEXT, $6)
+                      ELSE (C.name, C.id) < ($7::TEXT, $6)
+                    END)
+                ORDER BY CASE WHEN $5 = 'name' THEN C.name END ASC,
+                         CASE WHEN $5 = 'name_desc' THEN C.name END DESC,
+                         CASE WHEN $5 IN ('oldest', 'name') THEN C.id END ASC,
+                         C.id DESC
+                LIMIT $8::INT8 + 1
+            )
+            SELECT P.id as "id!: ItemId", P.owner as "owner!: Username", P.name as "name!", P.created as "created!",
+                   P.phase as "phase!: Phase", P.phase_changed as "phase_changed!", P.version as "version!",
+                   P.visibility as "visibility!: Visibility",
+                   (SELECT COUNT(*) FROM entries E WHERE E.contest = P.id) as "entry_count!",
+                   (SELECT COUNT(*) FROM all_judges(P.id)) as "judge_count!",
+                   CASE WHEN P.owner = $1 THEN 'owner' ELSE 'collaborator' END::access_role as "role!: Role",
+                   J.to_judge as "to_judge!", J.judged as "judged!", P.total as "total!"
+            FROM page P
+            CROSS JOIN LATERAL (
+                SELECT COUNT(*) as to_judge,
+                       COUNT(*) FILTER (WHERE CASE P.judging_mode
+                           WHEN 'scores' THEN EXISTS (SELECT 1 FROM scores S WHERE S.entry = E.id AND S.judge = $1)
+                           WHEN 'ballots' THEN EXISTS (SELECT 1 FROM ballot_rankings B WHERE B.entry = E.id AND B.judge = $1)
+                           ELSE EXISTS (SELECT 1 FROM comparisons X WHERE X.judge = $1 AND E.id IN (X.winner, X.loser))
+                       END) as judged
+                FROM entries E
+                WHERE E.contest = P.id AND entry_access_for_user($1, E.id) >= 'collaborator'
+            ) J
+            ORDER BY CASE WHEN $5 = 'name' THEN P.name END ASC,
+                     CASE WHEN $5 = 'name_desc' THEN P.name END DESC,
+                     CASE WHEN $5 IN ('oldest', 'name') THEN P.id END ASC,
+                     P.id DESC;
             "#,
-            self.username.as_str()
+            self.username.as_str(),
+            relation,
+            query.phase as Option<Phase>,
+            pattern,
+            sort,
+            after_id,
+            after_name,
+            limit
         ).fetch_all(db::pool())
-            .await
-            .map_err(api::Error::from)
+            .await?;
+
+        // Every row carries the same total, and an empty page has nothing left to count.
+        let total = rows.first().map_or(0, |r| r.total);
+        let mut items: Vec<ContestSummary> = rows.into_iter()
+            .map(|r| ContestSummary {
+                contest: Contest {
+                    id: r.id,
+                    owner: r.owner,
+                    name: r.name,
+                    created: r.created,
+                    phase: r.phase,
+                    phase_changed: r.phase_changed,
+                    version: r.version,
+                    visibility: r.visibility,
+                },
+                entry_count: r.entry_count,
+                judge_count: r.judge_count,
+                role: r.role,
+                progress: Progress {
+                    judged: r.judged,
+                    to_judge: r.to_judge,
+                },
+            })
+            .collect();
+
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items.last().map(|s| query.cursor_after(&s.contest))
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor, total })
     }
 
     /// Returns Ok if the user can access the resource.
@@ -0,0 +1,202 @@
+//! Checking that users own the email address they gave. A verification link carries a token
+//! signed with the server's key naming the user and the address it was sent to, so nothing needs
+//! storing until it's used. It's only accepted while that's still the user's address.
+
+use crate::model::ItemId;
+use crate::model::users::{User, Username};
+use crate::secure::JWT_KEY;
+use crate::{db, api, mail};
+use jwt_simple::prelude::{Claims, Duration, MACLike, VerificationOptions};
+use rocket::http::Status;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+/// How long a verification link works for.
+pub const LINK_HOURS: u64 = 24;
+/// How long to wait between verification emails to the same user.
+const RESEND_COOLDOWN_SECONDS: f64 = 60.0;
+/// How many verification emails a user can be sent in a day.
+const MAX_SENDS_PER_DAY: i64 = 5;
+/// Keeps verification tokens from being mistaken for anything else signed with the same key.
+const AUDIENCE: &str = "verify-email";
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("That verification link is invalid or has expired.")]
+    Invalid,
+    #[error("Your email address is already verified.")]
+    AlreadyVerified,
+    #[error("A verification email was sent less than a minute ago. Please wait before asking for another.")]
+    TooSoon,
+    #[error("Too many verification emails have been sent today. Please try again tomorrow.")]
+    TooMany,
+    #[error("{0} hasn't verified their email address, which this contest requires.")]
+    Unverified(Username),
+}
+
+impl api::ResponseError for Error {
+    fn status(&self) -> Status {
+        match self {
+            Error::Invalid => Status::BadRequest,
+            Error::AlreadyVerified => Status::Conflict,
+            Error::TooSoon |
+            Error::TooMany => Status::TooManyRequests,
+            Error::Unverified(_) => Status::Forbidden,
+        }
+    }
+
+    fn message(&self) -> Cow<'static, str> {
+        self.to_string().into()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VerificationClaims {
+    email: String,
+}
+
+pub struct EmailVerification;
+
+impl EmailVerification {
+    /// Emails `user` a link to verify their address, unless they've been sent too many already.
+    pub async fn send(user: &User) -> api::Result<()> {
+        if user.email_validated() {
+            return Err(Error::AlreadyVerified.into());
+        }
+
+        let mut tx = db::pool().begin().await?;
+        // Holding the user's row makes concurrent requests count their sends one at a time.
+        sqlx::query!(
+            r#"
+            SELECT username FROM users WHERE username = $1 FOR UPDATE;
+            "#,
+            user.username().as_str()
+        ).fetch_one(&mut tx)
+            .await?;
+
+        let recent = sqlx::query!(
+            r#"
+            SELECT count(*) as "count!", COALESCE(bool_or(sent > now() - make_interval(secs => $2)), false) as "too_soon!"
+            FROM email_verification_sends
+            WHERE username = $1 AND sent > now() - interval '1 day';
+            "#,
+            user.username().as_str(),
+            RESEND_COOLDOWN_SECONDS
+        ).fetch_one(&mut tx)
+            .await?;
+
+        if recent.too_soon {
+            return Err(Error::TooSoon.into());
+        }
+        if recent.count >= MAX_SENDS_PER_DAY {
+            return Err(Error::TooMany.into());
+        }
+
+        let sent = sqlx::query_scalar!(
+            r#"
+            INSERT INTO email_verification_sends (username) VALUES ($1) RETURNING id as "id: ItemId";
+            "#,
+            user.username().as_str()
+        ).fetch_one(&mut tx)
+            .await?;
+        tx.commit().await?;
+
+        let email = user.email().to_string();
+        let claims = Claims::with_custom_claims(VerificationClaims { email: email.clone() }, Duration::from_hours(LINK_HOURS))
+            .with_subject(user.username())
+            .with_audience(AUDIENCE);
+        let token = JWT_KEY.authenticate(claims).map_err(api::Error::from_error)?;
+        let link = mail::link(&format!("verify-email/{}", token));
+
+        let body = format!(
+            "Hi {},\n\n\
+             Please confirm this is your email address by following this link: {}\n\n\
+             The link expires in {} hours. If you didn't ask for this, you can ignore this email.\n",
+            user.display_name(),
+            link,
+            LINK_HOURS
+        );
+        // Sent outside the transaction so a slow mail server doesn't hold the user's row, but the
+        // send only counts if the email actually went out.
+        if let Err(e) = mail::send(&email, "Verify your email address", body).await {
+            sqlx::query!(
+                r#"
+                DELETE FROM email_verification_sends WHERE id = $1;
+                "#,
+                *sent
+            ).execute(db::pool())
+                .await?;
+            return Err(e);
+        }
+
+        info!(user = %user.username(), "sent verification email");
+        Ok(())
+    }
+
+    /// Marks the address a verification link was sent to as verified, returning whose it is.
+    pub async fn confirm(token: &str) -> api::Result<Username> {
+        let opts = VerificationOptions {
+            allowed_audiences: Some(std::iter::once(AUDIENCE.to_string()).collect()),
+            max_validity: Some(Duration::from_hours(LINK_HOURS)),
+            ..Default::default()
+        };
+        let claims = JWT_KEY.verify_token::<VerificationClaims>(token, Some(opts))
+            .map_err(|e| {info!("verification token rejected: {}", e); Error::Invalid})?;
+        let username = claims.subject.ok_or(Error::Invalid)?;
+
+        // A link for an address the user has since changed from is no good.
+        let verified = sqlx::query_scalar!(
+            r#"
+            UPDATE users SET email_validated = true WHERE username = $1 AND email = $2::TEXT::CITEXT RETURNING username;
+            "#,
+            username,
+            claims.custom.email
+        ).fetch_optional(db::pool())
+            .await?
+            .ok_or(Error::Invalid)?;
+
+        info!(user = %verified, "verified email address");
+        Ok(Username::try_from(verified)?)
+    }
+
+    /// Fails if `contest` only lets users with a verified email address take part and `user`
+    /// hasn't verified theirs.
+    pub async fn ensure_allowed(contest: ItemId, user: &Username) -> api::Result<()> {
+        let blocked = sqlx::query_scalar!(
+            r#"
+            SELECT C.require_verified_email AND NOT U.email_validated as "blocked!"
+            FROM contests C, users U
+            WHERE C.id = $1 AND U.username = $2;
+            "#,
+            *contest,
+            user.as_str()
+        ).fetch_optional(db::pool())
+            .await?;
+
+        match blocked {
+            Some(true) => Err(Error::Unverified(user.clone()).into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if `contest` requires verified email addresses and `creator` names an account that
+    /// hasn't verified its own. Creators are matched to accounts the same way conflicts match
+    /// them, ignoring case; creators without an account here have nothing to verify.
+    pub async fn ensure_entrant_allowed(contest: ItemId, creator: &str) -> api::Result<()> {
+        let blocked = sqlx::query_scalar!(
+            r#"
+            SELECT U.username as "username: Username"
+            FROM contests C, users U
+            WHERE C.id = $1 AND lower(U.username) = lower($2) AND C.require_verified_email AND NOT U.email_validated;
+            "#,
+            *contest,
+            creator
+        ).fetch_optional(db::pool())
+            .await?;
+
+        match blocked {
+            Some(user) => Err(Error::Unverified(user).into()),
+            None => Ok(()),
+        }
+    }
+}
\ No newline at end of file
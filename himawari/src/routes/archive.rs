@@ -0,0 +1,25 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::archive::{Archive, ImportRequest, ImportReport};
+use rocket::response::status;
+use rocket::http::Status;
+
+#[rocket::get("/contest/<contest_id>/export")]
+#[instrument(level = "info")]
+pub async fn export_contest(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Archive>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let contest = Contest::load(contest_id).await?;
+    Ok(json::Json(Archive::export(&contest, &access).await?))
+}
+
+#[rocket::post("/contest/import", format = "json", data = "<request>")]
+#[instrument(level = "info", skip(request))]
+pub async fn import_contest(id: RequestId, info: users::Info, request: json::Json<ImportRequest>) -> api::Result<status::Custom<json::Json<ImportReport>>> {
+    let res = request.0.archive.import(&info.username, request.0.owner.as_ref()).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
\ No newline at end of file
@@ -0,0 +1,61 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::{self, Contest};
+use crate::model::assignments::{Assignment, AssignmentSettings, Summary};
+use crate::http::{IfMatch, Tagged, Versioned};
+use validator::Validate;
+use rocket::http::Status;
+
+#[rocket::get("/contest/<contest_id>/assignments")]
+#[instrument(level = "info")]
+pub async fn get_assignments(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Assignment>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Ok(json::Json(Assignment::all_for_contest(contest_id).await?))
+}
+
+#[rocket::get("/contest/<contest_id>/assignments/mine")]
+#[instrument(level = "info")]
+pub async fn my_assignments(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Assignment>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Assignment::for_judge(contest_id, &info.username).await?))
+}
+
+/// The version of the contest being edited must be given, either as `If-Match` or as `version` in
+/// the body.
+#[rocket::put("/contest/<contest_id>/assignments", format = "json", data = "<settings>")]
+#[instrument(level = "info", skip(settings))]
+pub async fn configure_assignments(id: RequestId, contest_id: ItemId, info: users::Info, if_match: IfMatch, settings: json::Json<Versioned<AssignmentSettings>>) -> api::Result<Tagged<Summary>> {
+    let Versioned { body: settings, version } = settings.0;
+    settings.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+
+    let (version, stale) = contests::expected_version(if_match.0, version)?;
+    let contest = Contest::load(contest_id).await?;
+    let (version, summary) = Assignment::configure(&contest, &settings, version).await?
+        .ok_or(stale)?;
+    Ok(Tagged(summary, version))
+}
+
+#[rocket::post("/contest/<contest_id>/assignments/rebalance")]
+#[instrument(level = "info")]
+pub async fn rebalance_assignments(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Summary>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let contest = Contest::load(contest_id).await?;
+    Ok(json::Json(Assignment::rebalance(&contest).await?))
+}
+
+#[rocket::delete("/contest/<contest_id>/assignments")]
+#[instrument(level = "info")]
+pub async fn disable_assignments(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Assignment::disable(contest_id).await?;
+
+    Ok(Status::Ok)
+}
@@ -20,7 +20,7 @@ use crate::{
 };
 use secrecy::{SecretString, ExposeSecret};
 use rocket::http::Status;
-use jwt_simple::prelude::{HS256Key, Claims, Duration, MACLike, VerificationOptions};
+use jwt_simple::prelude::{Claims, Duration, MACLike, VerificationOptions};
 use std::convert::TryFrom;
 use rocket::request::{FromRequest, Outcome};
 use rocket::Request;
@@ -28,11 +28,43 @@ use rocket::http::hyper::header::AUTHORIZATION;
 use crate::api::ResponseError;
 use std::borrow::Cow;
 use crate::model::users::Info;
+use crate::model::ItemId;
+use crate::model::verification::EmailVerification;
+use crate::model::tokens::{self, TokenFamily, RevocationReason};
+use crate::secure::{SecretToken, JWT_KEY};
+use crate::http::ClientInfo;
 use crate::logging::RequestId;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Token {
     token: String,
+    #[serde(rename = "refreshToken")]
+    refresh_token: String,
+    /// Seconds until `token` expires.
+    #[serde(rename = "expiresIn")]
+    expires_in: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RefreshRequest {
+    #[serde(rename = "refreshToken")]
+    refresh_token: String,
+}
+
+/// What an access token vouches for.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct AccessClaims {
+    #[serde(flatten)]
+    info: Info,
+    family: ItemId,
+}
+
+/// A verified access token whose session hasn't been revoked.
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub info: Info,
+    /// The token family, or session, it was issued to.
+    pub family: ItemId,
 }
 
 impl AsRef<str> for Token {
@@ -41,27 +73,25 @@ impl AsRef<str> for Token {
     }
 }
 
-static JWT_KEY: Lazy<HS256Key> = Lazy::new(|| {
-    HS256Key::from_bytes(need_env_var("JWT_KEY").as_ref())
-});
-
-impl TryFrom<users::Info> for Token {
-    type Error = api::Error;
-
-    fn try_from(user: users::Info) -> Result<Self, Self::Error> {
-        let claims = Claims::with_custom_claims(user, Duration::from_days(7));
+impl Token {
+    /// Pairs a new access token for `user` with the family's latest refresh token.
+    fn issue(user: users::Info, family: ItemId, refresh: SecretToken) -> api::Result<Self> {
+        let lifetime = Duration::from_mins(tokens::ACCESS_TOKEN_MINUTES);
+        let claims = Claims::with_custom_claims(AccessClaims { info: user, family }, lifetime);
         Ok(Token {
-            token: JWT_KEY.authenticate(claims).map_err(api::Error::from_error)?
+            token: JWT_KEY.authenticate(claims).map_err(api::Error::from_error)?,
+            refresh_token: refresh.expose().to_string(),
+            expires_in: lifetime.as_secs(),
         })
     }
 }
 
-impl<'s> TryFrom<&'s str> for users::Info {
+impl<'s> TryFrom<&'s str> for AccessClaims {
     type Error = api::Error;
 
     fn try_from(s: &'s str) -> Result<Self, Self::Error> {
         let opts = VerificationOptions {
-            max_validity: Some(Duration::from_days(7)),
+            max_validity: Some(Duration::from_mins(tokens::ACCESS_TOKEN_MINUTES)),
             ..Default::default()
         };
 
@@ -75,6 +105,7 @@ impl<'s> TryFrom<&'s str> for users::Info {
 pub enum TokenFailure {
     Malformed,
     Missing,
+    Revoked,
 }
 
 impl ResponseError for TokenFailure {
@@ -86,19 +117,20 @@ impl ResponseError for TokenFailure {
         match self {
             TokenFailure::Malformed => {"Token was malformed"}
             TokenFailure::Missing => {"No token found"}
+            TokenFailure::Revoked => {"Token has been revoked"}
         }.into()
     }
 }
 
 #[async_trait::async_trait]
-impl<'r> FromRequest<'r> for users::Info {
+impl<'r> FromRequest<'r> for AccessToken {
     type Error = api::Error;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let req = request.guard::<RequestId>().await.unwrap();
         let span = info_span!("parsing JWT", id=%req);
-        span.in_scope(|| {
-            let res = request.headers().get_one(AUTHORIZATION.as_str())
+        let claims = span.in_scope(|| {
+            request.headers().get_one(AUTHORIZATION.as_str())
                 .ok_or_else(|| {info!("missing auth header"); TokenFailure::Missing.into()})
                 .and_then(|s|
                     {
@@ -108,18 +140,53 @@ impl<'r> FromRequest<'r> for users::Info {
                         }
                         Ok(s[7..].trim())
                     })
-                .and_then(users::Info::try_from);
+                .and_then(AccessClaims::try_from)
+        });
 
-            match res {
-                Ok(inf) => {Outcome::Success(inf)}
-                Err(e) => {
-                    Outcome::Failure((e.as_inner().status(), e))
+        // The denylist: every access token from a revoked family is refused.
+        let res: api::Result<AccessToken> = match claims {
+            Ok(claims) => match TokenFamily::touch(claims.family).await {
+                Ok(true) => Ok(AccessToken { info: claims.info, family: claims.family }),
+                Ok(false) => {
+                    span.in_scope(|| info!(session = %claims.family, "session revoked"));
+                    Err(TokenFailure::Revoked.into())
                 }
+                Err(e) => Err(e.into()),
+            },
+            Err(e) => Err(e),
+        };
+
+        match res {
+            Ok(token) => {Outcome::Success(token)}
+            Err(e) => {
+                Outcome::Failure((e.as_inner().status(), e))
             }
-        })
+        }
     }
 }
 
+#[async_trait::async_trait]
+impl<'r> FromRequest<'r> for users::Info {
+    type Error = api::Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        request.guard::<AccessToken>().await.map(|token| token.info)
+    }
+}
+
+/// Lets routes that are readable anonymously still know who's asking. A token that's present but
+/// invalid is rejected rather than treated as anonymous.
+#[async_trait::async_trait]
+impl<'r> FromRequest<'r> for users::Viewer {
+    type Error = api::Error;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if request.headers().get_one(AUTHORIZATION.as_str()).is_none() {
+            return Outcome::Success(users::Viewer(None));
+        }
+        request.guard::<users::Info>().await.map(|info| users::Viewer(Some(info)))
+    }
+}
 
 #[rocket::post("/register", format = "json", data = "<registration>")]
 #[instrument(level = "info", skip(registration), fields(user = % registration.username))]
@@ -138,12 +205,18 @@ pub async fn register(id: RequestId, registration: json::Json<NewUserRequest>) -
         pass_hash
     ).execute(db::pool()).await?;
 
+    // The account is usable either way, and the user can ask for another link.
+    let user = User::load_full(&username).await?;
+    if let Err(e) = EmailVerification::send(&user).await {
+        warn!(error = ?e, "couldn't send verification email");
+    }
+
     Ok(Status::Created)
 }
 
 #[rocket::post("/login", format = "json", data = "<login>")]
 #[instrument(level = "info", skip(login), fields(user = % login.username))]
-pub async fn login(id: RequestId, login: json::Json<LoginRequest>) -> api::Result<json::Json<Token>> {
+pub async fn login(id: RequestId, client: ClientInfo, login: json::Json<LoginRequest>) -> api::Result<json::Json<Token>> {
     info!("login attempt");
     let LoginRequest { password, username } = login.0;
     let user = User::load_full(&username).await?;
@@ -156,11 +229,30 @@ pub async fn login(id: RequestId, login: json::Json<LoginRequest>) -> api::Resul
             Err(Status::Unauthorized.into())
         }
         Verification::Passed => {
-            Ok(json::Json(Token::try_from(users::Info::from(user))?))
+            let (family, refresh) = TokenFamily::start(user.username(), &client).await?;
+            Ok(json::Json(Token::issue(users::Info::from(user), family, refresh)?))
         }
     }
 }
 
+/// Trades a refresh token for a new access token and a replacement refresh token. The user's
+/// details are reloaded, so the new access token reflects any changes to them.
+#[rocket::post("/token/refresh", format = "json", data = "<refresh>")]
+#[instrument(level = "info", skip(refresh))]
+pub async fn refresh(id: RequestId, client: ClientInfo, refresh: json::Json<RefreshRequest>) -> api::Result<json::Json<Token>> {
+    let (family, username, next) = TokenFamily::rotate(&refresh.0.refresh_token, &client).await?;
+    let user = User::load_full(&username).await?;
+    Ok(json::Json(Token::issue(users::Info::from(user), family, next)?))
+}
+
+/// Revokes the caller's access and refresh tokens.
+#[rocket::post("/logout")]
+#[instrument(level = "info")]
+pub async fn logout(id: RequestId, token: AccessToken) -> api::Result<Status> {
+    TokenFamily::revoke(token.family, RevocationReason::Logout).await?;
+    Ok(Status::Ok)
+}
+
 static HASH_KEY: Lazy<secrecy::SecretString> = Lazy::new(|| SecretString::new(need_env_var("HASH_KEY")));
 static ARGON_CONTEXT: Lazy<Argon2> = Lazy::new(|| {
     Argon2::new(
@@ -0,0 +1,35 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::verification::EmailVerification;
+use crate::model::ballots::{Ballot, BallotSubmission};
+use rocket::http::Status;
+
+#[rocket::get("/contest/<contest_id>/ballot")]
+#[instrument(level = "info")]
+pub async fn my_ballot(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Ballot>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Ballot::load(contest_id, &info.username).await?))
+}
+
+#[rocket::put("/contest/<contest_id>/ballot", format = "json", data = "<ballot>")]
+#[instrument(level = "info", skip(ballot))]
+pub async fn submit_ballot(id: RequestId, contest_id: ItemId, info: users::Info, ballot: json::Json<BallotSubmission>) -> api::Result<json::Json<Ballot>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    EmailVerification::ensure_allowed(contest_id, &info.username).await?;
+    Ok(json::Json(Ballot::submit(contest_id, &info.username, ballot.0.ranking).await?))
+}
+
+#[rocket::delete("/contest/<contest_id>/ballot")]
+#[instrument(level = "info")]
+pub async fn withdraw_ballot(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ballot::withdraw(contest_id, &info.username).await?;
+
+    Ok(Status::Ok)
+}
@@ -0,0 +1,37 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::verification::EmailVerification;
+use crate::model::comparisons::{Comparison, ComparisonSubmission, Pair};
+use rocket::response::status;
+use rocket::http::Status;
+
+#[rocket::get("/contest/<contest_id>/comparisons/next")]
+#[instrument(level = "info")]
+pub async fn next_pair(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<Option<json::Json<Pair>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    let contest = Contest::load(contest_id).await?;
+    Ok(Comparison::next_pair(&contest, &info.username).await?.map(json::Json))
+}
+
+#[rocket::get("/contest/<contest_id>/comparisons")]
+#[instrument(level = "info")]
+pub async fn my_comparisons(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Comparison>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Comparison::for_judge(contest_id, &info.username).await?))
+}
+
+#[rocket::post("/contest/<contest_id>/comparisons", format = "json", data = "<comparison>")]
+#[instrument(level = "info", skip(comparison))]
+pub async fn new_comparison(id: RequestId, contest_id: ItemId, info: users::Info, comparison: json::Json<ComparisonSubmission>) -> api::Result<status::Custom<json::Json<Comparison>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    EmailVerification::ensure_allowed(contest_id, &info.username).await?;
+    let res = Comparison::record(contest_id, &info.username, &comparison.0).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
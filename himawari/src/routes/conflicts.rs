@@ -0,0 +1,65 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure, db};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::conflicts::{self, Conflict, NewConflict};
+use crate::model::entries::Entry;
+use validator::Validate;
+use rocket::response::status;
+use rocket::http::Status;
+
+/// Owners see every conflict in the contest; judges only see their own.
+#[rocket::get("/contest/<contest_id>/conflicts")]
+#[instrument(level = "info")]
+pub async fn get_conflicts(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Conflict>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+
+    let out = if access >= secure::Role::Owner {
+        Conflict::for_contest(contest_id).await?
+    } else {
+        Conflict::for_judge(contest_id, &info.username).await?
+    };
+    Ok(json::Json(out))
+}
+
+#[rocket::post("/contest/<contest_id>/conflicts", format = "json", data = "<conflict>")]
+#[instrument(level = "info", skip(conflict))]
+pub async fn declare_conflict(id: RequestId, contest_id: ItemId, info: users::Info, conflict: json::Json<NewConflict>) -> api::Result<status::Custom<json::Json<Conflict>>> {
+    conflict.0.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+
+    let judge = conflict.0.judge.clone().unwrap_or_else(|| info.username.clone());
+    if judge != info.username {
+        access.ensure_at_least(secure::Role::Owner)?;
+    }
+    // Seeing which entries vanish after guessing at creators would undo the blinding.
+    if conflict.0.creator.is_some() && Entry::identity_hidden_from(contest_id, &access).await? {
+        return Err(conflicts::Error::CreatorHidden.into());
+    }
+
+    let contest = Contest::load(contest_id).await?;
+    let res = Conflict::declare(&contest, &judge, &info.username, &conflict.0).await?;
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
+
+/// Judges can withdraw conflicts they declared themselves, but not ones the owner added.
+#[rocket::delete("/contest/<contest_id>/conflicts/<conflict_id>")]
+#[instrument(level = "info")]
+pub async fn withdraw_conflict(id: RequestId, contest_id: ItemId, conflict_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+
+    let conflict = Conflict::load(conflict_id).await?;
+    if conflict.contest != contest_id {
+        return Err(db::Error::NotFound.into());
+    }
+    if conflict.declared_by != info.username {
+        access.ensure_at_least(secure::Role::Owner)?;
+    }
+
+    Conflict::withdraw(conflict_id).await?;
+    Ok(Status::Ok)
+}
@@ -2,16 +2,18 @@ use crate::logging::RequestId;
 use crate::model::{users, ItemId, RawItemId};
 use crate::{api, db, secure};
 use rocket::serde::json;
-use crate::model::contests::Contest;
+use crate::model::contests::{self, Contest, ContestQuery, ContestSummary, Page, Phase, PhaseChange, Deadlines, Requirements, TrashedContest, Visibility};
+use crate::http::{IfMatch, Tagged, Versioned};
 use validator::Validate;
 use rocket::response::status;
 use rocket::http::Status;
 
 
-#[rocket::get("/contest")]
+#[rocket::get("/contest?<query..>")]
 #[instrument(level = "info")]
-pub async fn my_contests(id: RequestId, info: users::Info) -> api::Result<json::Json<Vec<Contest>>> {
-    let contests = info.contests().await?;
+pub async fn my_contests(id: RequestId, info: users::Info, query: ContestQuery) -> api::Result<json::Json<Page<ContestSummary>>> {
+    query.validate()?;
+    let contests = info.contests(&query).await?;
     Ok(json::Json(contests))
 }
 
@@ -19,6 +21,14 @@ pub async fn my_contests(id: RequestId, info: users::Info) -> api::Result<json::
 pub struct NewContest {
     #[validate(length(min = 1, max = 1024), non_control_character)]
     name: String,
+    #[serde(default)]
+    visibility: Visibility,
+}
+
+#[rocket::get("/contest/public")]
+#[instrument(level = "info")]
+pub async fn public_contests(id: RequestId) -> api::Result<json::Json<Vec<Contest>>> {
+    Ok(json::Json(Contest::public().await?))
 }
 
 #[rocket::post("/contest", format = "json", data = "<contest>")]
@@ -28,10 +38,13 @@ pub async fn new_contest(id: RequestId, info: users::Info, contest: json::Json<N
     let res = sqlx::query_as!(
         Contest,
         r#"
-        INSERT INTO contests (owner, name) VALUES ($1, $2) RETURNING id as "id: _", owner as "owner: _", name, created;
+        INSERT INTO contests (owner, name, visibility) VALUES ($1, $2, $3) 
+        RETURNING id as "id: _", owner as "owner: _", name, created, phase as "phase: _", phase_changed, version,
+                  visibility as "visibility: _";
         "#,
         info.username.as_str(),
-        contest.0.name
+        contest.0.name,
+        contest.0.visibility as Visibility
     ).fetch_one(db::pool())
         .await?;
 
@@ -40,10 +53,45 @@ pub async fn new_contest(id: RequestId, info: users::Info, contest: json::Json<N
 
 #[rocket::get("/contest/<contest_id>")]
 #[instrument(level = "info")]
-pub async fn get_contest(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Contest>> {
+pub async fn get_contest(id: RequestId, contest_id: ItemId, viewer: users::Viewer) -> api::Result<Tagged<Contest>> {
+    let access = viewer.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Viewer)?;
+    let contest = Contest::load(contest_id).await?;
+    let version = contest.version;
+    Ok(Tagged(contest, version))
+}
+
+/// Fields left out keep their current values. The version being edited must be given, either as
+/// `If-Match` (a stale one gets 412) or as `version` in the body (a stale one gets 409).
+#[derive(Deserialize)]
+pub struct ContestPatch {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    visibility: Option<Visibility>,
+    #[serde(default)]
+    version: Option<i64>,
+}
+
+#[rocket::patch("/contest/<contest_id>", format = "json", data = "<patch>")]
+#[instrument(level = "info", skip(patch))]
+pub async fn update_contest(id: RequestId, contest_id: ItemId, info: users::Info, if_match: IfMatch, patch: json::Json<ContestPatch>) -> api::Result<Tagged<Contest>> {
     let access = info.access_level::<Contest>(&contest_id).await?;
-    access.ensure_at_least(secure::Role::Collaborator)?;
-    Ok(json::Json(Contest::load(contest_id).await?))
+    access.ensure_at_least(secure::Role::Owner)?;
+
+    let (version, stale) = contests::expected_version(if_match.0, patch.0.version)?;
+
+    let current = Contest::load(contest_id).await?;
+    let updated = NewContest {
+        name: patch.0.name.unwrap_or(current.name),
+        visibility: patch.0.visibility.unwrap_or(current.visibility),
+    };
+    updated.validate()?;
+
+    let contest = Contest::update(contest_id, &updated.name, updated.visibility, version).await?
+        .ok_or(stale)?;
+    let version = contest.version;
+    Ok(Tagged(contest, version))
 }
 
 #[rocket::delete("/contest/<contest_id>")]
@@ -51,7 +99,84 @@ pub async fn get_contest(id: RequestId, contest_id: ItemId, info: users::Info) -
 pub async fn delete_contest(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<Status> {
     let access = info.access_level::<Contest>(&contest_id).await?;
     access.ensure_at_least(secure::Role::Owner)?;
-    Contest::delete(contest_id).await?;
+    Contest::trash(contest_id).await?;
+
+    Ok(Status::Ok)
+}
+
+#[rocket::get("/contest/trash")]
+#[instrument(level = "info")]
+pub async fn trashed_contests(id: RequestId, info: users::Info) -> api::Result<json::Json<Vec<TrashedContest>>> {
+    Ok(json::Json(Contest::trashed(&info.username).await?))
+}
+
+/// Trashed contests are invisible to access checks, so this only checks ownership directly.
+#[rocket::post("/contest/<contest_id>/restore")]
+#[instrument(level = "info")]
+pub async fn restore_contest(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Contest>> {
+    Ok(json::Json(Contest::restore(contest_id, &info.username).await?))
+}
+
+#[derive(Deserialize)]
+pub struct PhaseRequest {
+    phase: Phase,
+}
+
+#[rocket::post("/contest/<contest_id>/phase", format = "json", data = "<request>")]
+#[instrument(level = "info", skip(request))]
+pub async fn change_phase(id: RequestId, contest_id: ItemId, info: users::Info, request: json::Json<PhaseRequest>) -> api::Result<json::Json<Contest>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Ok(json::Json(Contest::change_phase(contest_id, request.0.phase, &info.username).await?))
+}
+
+#[rocket::get("/contest/<contest_id>/phases")]
+#[instrument(level = "info")]
+pub async fn phase_history(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<PhaseChange>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Contest::phase_history(contest_id).await?))
+}
+
+#[rocket::get("/contest/<contest_id>/deadlines")]
+#[instrument(level = "info")]
+pub async fn get_deadlines(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Deadlines>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Contest::deadlines(contest_id).await?))
+}
+
+/// Like [`update_contest`], this needs the version of the contest being edited.
+#[rocket::put("/contest/<contest_id>/deadlines", format = "json", data = "<deadlines>")]
+#[instrument(level = "info", skip(deadlines))]
+pub async fn set_deadlines(id: RequestId, contest_id: ItemId, info: users::Info, if_match: IfMatch, deadlines: json::Json<Versioned<Deadlines>>) -> api::Result<Tagged<Deadlines>> {
+    let Versioned { body: deadlines, version } = deadlines.0;
+    deadlines.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+
+    let (version, stale) = contests::expected_version(if_match.0, version)?;
+    let version = Contest::set_deadlines(contest_id, &deadlines, version).await?
+        .ok_or(stale)?;
+    Ok(Tagged(deadlines, version))
+}
+
+#[rocket::get("/contest/<contest_id>/requirements")]
+#[instrument(level = "info")]
+pub async fn get_requirements(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Requirements>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Contest::requirements(contest_id).await?))
+}
+
+/// Requiring verified email addresses doesn't remove existing judges who haven't verified theirs,
+/// but they can't judge until they do.
+#[rocket::put("/contest/<contest_id>/requirements", format = "json", data = "<requirements>")]
+#[instrument(level = "info", skip(requirements))]
+pub async fn set_requirements(id: RequestId, contest_id: ItemId, info: users::Info, requirements: json::Json<Requirements>) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Contest::set_requirements(contest_id, &requirements.0).await?;
 
     Ok(Status::Ok)
 }
\ No newline at end of file
@@ -0,0 +1,86 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::verification::EmailVerification;
+use crate::model::entries::{Entry, NewEntry, EntryFields, EntrySchema};
+use validator::Validate;
+use rocket::response::status;
+use rocket::http::Status;
+
+#[rocket::post("/entry", format = "json", data = "<entry>")]
+#[instrument(level = "info", skip(entry))]
+pub async fn new_entry(id: RequestId, info: users::Info, entry: json::Json<NewEntry>) -> api::Result<status::Custom<json::Json<Entry>>> {
+    entry.0.validate()?;
+    let access = info.access_level::<Contest>(&entry.0.contest).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    EmailVerification::ensure_allowed(entry.0.contest, &info.username).await?;
+    let res = Entry::create(&entry.0).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
+
+#[rocket::get("/entry/<entry_id>")]
+#[instrument(level = "info")]
+pub async fn get_entry(id: RequestId, entry_id: ItemId, viewer: users::Viewer) -> api::Result<json::Json<Entry>> {
+    let access = viewer.access_level::<Entry>(&entry_id).await?;
+    access.ensure_at_least(secure::Role::Viewer)?;
+    let entry = Entry::load(entry_id).await?;
+    if Entry::identity_hidden_from(entry.contest, &access).await? {
+        let schema = EntrySchema::load(entry.contest).await?;
+        return Ok(json::Json(entry.blinded(&schema)));
+    }
+    Ok(json::Json(entry))
+}
+
+#[rocket::put("/entry/<entry_id>", format = "json", data = "<entry>")]
+#[instrument(level = "info", skip(entry))]
+pub async fn update_entry(id: RequestId, entry_id: ItemId, info: users::Info, entry: json::Json<EntryFields>) -> api::Result<json::Json<Entry>> {
+    entry.0.validate()?;
+    let access = info.access_level::<Entry>(&entry_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Ok(json::Json(Entry::update(entry_id, &entry.0).await?))
+}
+
+#[rocket::delete("/entry/<entry_id>")]
+#[instrument(level = "info")]
+pub async fn delete_entry(id: RequestId, entry_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Entry>(&entry_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Entry::delete(entry_id).await?;
+
+    Ok(Status::Ok)
+}
+
+#[rocket::get("/contest/<contest_id>/entries")]
+#[instrument(level = "info")]
+pub async fn contest_entries(id: RequestId, contest_id: ItemId, viewer: users::Viewer) -> api::Result<json::Json<Vec<Entry>>> {
+    let access = viewer.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Viewer)?;
+    let mut entries = Entry::visible_to(contest_id, viewer.username()).await?;
+    if Entry::identity_hidden_from(contest_id, &access).await? {
+        let schema = EntrySchema::load(contest_id).await?;
+        entries = entries.into_iter().map(|e| e.blinded(&schema)).collect();
+    }
+    Ok(json::Json(entries))
+}
+
+
+#[rocket::get("/contest/<contest_id>/entry-schema")]
+#[instrument(level = "info")]
+pub async fn get_entry_schema(id: RequestId, contest_id: ItemId, viewer: users::Viewer) -> api::Result<json::Json<EntrySchema>> {
+    let access = viewer.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Viewer)?;
+    Ok(json::Json(EntrySchema::load(contest_id).await?))
+}
+
+#[rocket::put("/contest/<contest_id>/entry-schema", format = "json", data = "<schema>")]
+#[instrument(level = "info", skip(schema))]
+pub async fn set_entry_schema(id: RequestId, contest_id: ItemId, info: users::Info, schema: json::Json<EntrySchema>) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    schema.0.save(contest_id).await?;
+
+    Ok(Status::Ok)
+}
\ No newline at end of file
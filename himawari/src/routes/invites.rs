@@ -0,0 +1,50 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::invites::{Invite, NewInvite, CreatedInvite};
+use validator::Validate;
+use rocket::response::status;
+use rocket::http::Status;
+
+#[derive(Deserialize)]
+pub struct InviteAcceptance {
+    token: String,
+}
+
+#[rocket::get("/contest/<contest_id>/invites")]
+#[instrument(level = "info")]
+pub async fn get_invites(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Invite>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Ok(json::Json(Invite::for_contest(contest_id).await?))
+}
+
+#[rocket::post("/contest/<contest_id>/invites", format = "json", data = "<invite>")]
+#[instrument(level = "info", skip(invite))]
+pub async fn new_invite(id: RequestId, contest_id: ItemId, info: users::Info, invite: json::Json<NewInvite>) -> api::Result<status::Custom<json::Json<CreatedInvite>>> {
+    invite.0.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let contest = Contest::load(contest_id).await?;
+    let res = Invite::create(&contest, &info.username, &invite.0).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
+
+#[rocket::delete("/contest/<contest_id>/invites/<invite_id>")]
+#[instrument(level = "info")]
+pub async fn revoke_invite(id: RequestId, contest_id: ItemId, invite_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Invite::revoke(contest_id, invite_id).await?;
+
+    Ok(Status::Ok)
+}
+
+#[rocket::post("/invite/accept", format = "json", data = "<acceptance>")]
+#[instrument(level = "info", skip(acceptance))]
+pub async fn accept_invite(id: RequestId, info: users::Info, acceptance: json::Json<InviteAcceptance>) -> api::Result<json::Json<Contest>> {
+    Ok(json::Json(Invite::accept(&acceptance.0.token, &info.username).await?))
+}
@@ -0,0 +1,46 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::model::users::Username;
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::verification::EmailVerification;
+use crate::model::contests::{Contest, Judge};
+use rocket::response::status;
+use rocket::http::Status;
+
+#[derive(Deserialize)]
+pub struct NewJudge {
+    username: Username,
+}
+
+#[rocket::get("/contest/<contest_id>/judges")]
+#[instrument(level = "info")]
+pub async fn get_judges(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Judge>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let contest = Contest::load(contest_id).await?;
+    Ok(json::Json(contest.invited_judges().await?))
+}
+
+#[rocket::post("/contest/<contest_id>/judges", format = "json", data = "<judge>")]
+#[instrument(level = "info", skip(judge))]
+pub async fn add_judge(id: RequestId, contest_id: ItemId, info: users::Info, judge: json::Json<NewJudge>) -> api::Result<status::Custom<json::Json<Judge>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    EmailVerification::ensure_allowed(contest_id, &judge.0.username).await?;
+    let contest = Contest::load(contest_id).await?;
+    let res = contest.add_judge(&judge.0.username).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
+
+#[rocket::delete("/contest/<contest_id>/judges/<username>")]
+#[instrument(level = "info")]
+pub async fn remove_judge(id: RequestId, contest_id: ItemId, username: Username, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let contest = Contest::load(contest_id).await?;
+    contest.remove_judge(&username).await?;
+
+    Ok(Status::Ok)
+}
@@ -1,4 +1,19 @@
 
 pub mod auth;
 pub mod debug;
-pub mod contests;
\ No newline at end of file
+pub mod contests;
+pub mod entries;
+pub mod scores;
+pub mod results;
+pub mod ballots;
+pub mod comparisons;
+pub mod assignments;
+pub mod conflicts;
+pub mod judges;
+pub mod invites;
+pub mod transfers;
+pub mod archive;
+pub mod sessions;
+pub mod verification;
+pub mod passwords;
+pub mod profile;
\ No newline at end of file
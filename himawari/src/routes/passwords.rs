@@ -0,0 +1,43 @@
+use crate::logging::RequestId;
+use crate::model::users::{Email, Password};
+use crate::model::resets::PasswordReset;
+use crate::routes::auth::hash_password;
+use crate::api;
+use rocket::serde::json;
+use rocket::http::Status;
+
+#[derive(Deserialize)]
+pub struct ForgotRequest {
+    email: Email,
+}
+
+/// Always accepted, whether or not the address has an account. The email is sent in the
+/// background, so how long this takes doesn't give it away either.
+#[rocket::post("/password/forgot", format = "json", data = "<request>")]
+#[instrument(level = "info", skip(request))]
+pub async fn forgot_password(id: RequestId, request: json::Json<ForgotRequest>) -> Status {
+    let email = request.0.email;
+    tokio::spawn(async move {
+        if let Err(e) = PasswordReset::request(&email).await {
+            error!(error = ?e, "password reset request failed");
+        }
+    });
+    Status::Accepted
+}
+
+#[derive(Deserialize)]
+pub struct ResetRequest {
+    token: String,
+    password: Password,
+}
+
+/// Sets a new password and logs the user out everywhere, so they need to log in again.
+#[rocket::post("/password/reset", format = "json", data = "<request>")]
+#[instrument(level = "info", skip(request))]
+pub async fn reset_password(id: RequestId, request: json::Json<ResetRequest>) -> api::Result<Status> {
+    let ResetRequest { token, password } = request.0;
+    PasswordReset::ensure_usable(&token).await?;
+    let hash = hash_password(password).await?;
+    PasswordReset::complete(&token, hash).await?;
+    Ok(Status::Ok)
+}
\ No newline at end of file
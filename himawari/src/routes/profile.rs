@@ -0,0 +1,87 @@
+use crate::logging::RequestId;
+use crate::model::users::{self, Email, Password, User, Username};
+use crate::model::verification::EmailVerification;
+use crate::routes::auth::{hash_password, verify_password, AccessToken, Verification};
+use crate::api;
+use rocket::serde::json;
+use rocket::http::Status;
+
+#[rocket::get("/profile")]
+#[instrument(level = "info")]
+pub async fn my_profile(id: RequestId, info: users::Info) -> api::Result<json::Json<users::Info>> {
+    let user = User::load_full(&info.username).await?;
+    Ok(json::Json(users::Info::from(user)))
+}
+
+#[derive(Deserialize)]
+pub struct ProfilePatch {
+    #[serde(rename = "displayName")]
+    display_name: Username,
+}
+
+/// Like every change here, this shows up in access tokens once they're refreshed.
+#[rocket::patch("/profile", format = "json", data = "<patch>")]
+#[instrument(level = "info", skip(patch))]
+pub async fn update_profile(id: RequestId, info: users::Info, patch: json::Json<ProfilePatch>) -> api::Result<json::Json<users::Info>> {
+    User::set_display_name(&info.username, &patch.0.display_name).await?;
+    let user = User::load_full(&info.username).await?;
+    Ok(json::Json(users::Info::from(user)))
+}
+
+#[derive(Deserialize)]
+pub struct PasswordChange {
+    #[serde(rename = "currentPassword")]
+    current_password: Password,
+    #[serde(rename = "newPassword")]
+    new_password: Password,
+}
+
+/// Logs out every other session, but keeps the one making the change. Reset links already sent
+/// stop working.
+#[rocket::put("/profile/password", format = "json", data = "<change>")]
+#[instrument(level = "info", skip(change))]
+pub async fn change_password(id: RequestId, token: AccessToken, change: json::Json<PasswordChange>) -> api::Result<Status> {
+    let PasswordChange { current_password, new_password } = change.0;
+    let username = &token.info.username;
+    let user = User::load_full(username).await?;
+
+    if let Verification::Failed = verify_password(current_password, user.hash().to_string()).await? {
+        return Err(Status::Forbidden.into());
+    }
+
+    let hash = hash_password(new_password).await?;
+    let sessions = User::change_password(username, hash, token.family).await?;
+    info!(sessions, "changed password");
+
+    Ok(Status::Ok)
+}
+
+#[derive(Deserialize)]
+pub struct EmailChange {
+    email: Email,
+    #[serde(rename = "currentPassword")]
+    current_password: Password,
+}
+
+/// A new address is unverified until the user follows the link sent to it.
+#[rocket::put("/profile/email", format = "json", data = "<change>")]
+#[instrument(level = "info", skip(change))]
+pub async fn change_email(id: RequestId, info: users::Info, change: json::Json<EmailChange>) -> api::Result<json::Json<users::Info>> {
+    let EmailChange { email, current_password } = change.0;
+    let user = User::load_full(&info.username).await?;
+
+    if let Verification::Failed = verify_password(current_password, user.hash().to_string()).await? {
+        return Err(Status::Forbidden.into());
+    }
+
+    User::set_email(&info.username, &email).await?;
+    let user = User::load_full(&info.username).await?;
+    if !user.email_validated() {
+        // The change stands either way, and the user can ask for another link.
+        if let Err(e) = EmailVerification::send(&user).await {
+            warn!(error = ?e, "couldn't send verification email");
+        }
+    }
+
+    Ok(json::Json(users::Info::from(user)))
+}
\ No newline at end of file
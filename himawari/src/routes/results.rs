@@ -0,0 +1,49 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::{self, Contest, Phase};
+use crate::model::results::{Results, ScoringSettings};
+use crate::http::{IfMatch, Tagged, Versioned};
+use validator::Validate;
+
+#[rocket::get("/contest/<contest_id>/scoring")]
+#[instrument(level = "info")]
+pub async fn get_scoring(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<ScoringSettings>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(ScoringSettings::load(contest_id).await?))
+}
+
+/// The version of the contest being edited must be given, either as `If-Match` or as `version` in
+/// the body.
+#[rocket::put("/contest/<contest_id>/scoring", format = "json", data = "<settings>")]
+#[instrument(level = "info", skip(settings))]
+pub async fn set_scoring(id: RequestId, contest_id: ItemId, info: users::Info, if_match: IfMatch, settings: json::Json<Versioned<ScoringSettings>>) -> api::Result<Tagged<ScoringSettings>> {
+    let Versioned { body: settings, version } = settings.0;
+    settings.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+
+    let (version, stale) = contests::expected_version(if_match.0, version)?;
+    let version = settings.save(contest_id, version).await?
+        .ok_or(stale)?;
+    Ok(Tagged(settings, version))
+}
+
+/// The owner can see results from deliberation onwards; everyone who can see the contest once
+/// they're published.
+#[rocket::get("/contest/<contest_id>/results")]
+#[instrument(level = "info")]
+pub async fn get_results(id: RequestId, contest_id: ItemId, viewer: users::Viewer) -> api::Result<json::Json<Results>> {
+    let access = viewer.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Viewer)?;
+    let contest = Contest::load(contest_id).await?;
+    if contest.phase < Phase::Published {
+        access.ensure_at_least(secure::Role::Owner)?;
+        if contest.phase < Phase::Deliberation {
+            return Err(contests::Error::WrongPhase(Phase::Deliberation).into());
+        }
+    }
+    Ok(json::Json(Results::compute(&contest).await?))
+}
@@ -0,0 +1,75 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::entries::Entry;
+use crate::model::verification::EmailVerification;
+use crate::model::scores::{Criterion, CriterionFields, Score, ScoreSubmission};
+use validator::Validate;
+use rocket::response::status;
+use rocket::http::Status;
+
+#[rocket::get("/contest/<contest_id>/rubric")]
+#[instrument(level = "info")]
+pub async fn get_rubric(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Criterion>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Criterion::for_contest(contest_id).await?))
+}
+
+#[rocket::post("/contest/<contest_id>/rubric", format = "json", data = "<criterion>")]
+#[instrument(level = "info", skip(criterion))]
+pub async fn new_criterion(id: RequestId, contest_id: ItemId, info: users::Info, criterion: json::Json<CriterionFields>) -> api::Result<status::Custom<json::Json<Criterion>>> {
+    criterion.0.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let res = Criterion::create(contest_id, &criterion.0).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
+
+#[rocket::put("/contest/<contest_id>/rubric/<criterion_id>", format = "json", data = "<criterion>")]
+#[instrument(level = "info", skip(criterion))]
+pub async fn update_criterion(id: RequestId, contest_id: ItemId, criterion_id: ItemId, info: users::Info, criterion: json::Json<CriterionFields>) -> api::Result<json::Json<Criterion>> {
+    criterion.0.validate()?;
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Ok(json::Json(Criterion::update(contest_id, criterion_id, &criterion.0).await?))
+}
+
+#[rocket::delete("/contest/<contest_id>/rubric/<criterion_id>")]
+#[instrument(level = "info")]
+pub async fn delete_criterion(id: RequestId, contest_id: ItemId, criterion_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Criterion::delete(contest_id, criterion_id).await?;
+
+    Ok(Status::Ok)
+}
+
+#[rocket::get("/contest/<contest_id>/scores")]
+#[instrument(level = "info")]
+pub async fn my_contest_scores(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Score>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Score::for_contest(contest_id, &info.username).await?))
+}
+
+#[rocket::get("/entry/<entry_id>/scores")]
+#[instrument(level = "info")]
+pub async fn my_entry_scores(id: RequestId, entry_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Score>>> {
+    let access = info.access_level::<Entry>(&entry_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    Ok(json::Json(Score::for_entry(entry_id, &info.username).await?))
+}
+
+#[rocket::put("/entry/<entry_id>/scores", format = "json", data = "<scores>")]
+#[instrument(level = "info", skip(scores))]
+pub async fn submit_scores(id: RequestId, entry_id: ItemId, info: users::Info, scores: json::Json<Vec<ScoreSubmission>>) -> api::Result<json::Json<Vec<Score>>> {
+    let access = info.access_level::<Entry>(&entry_id).await?;
+    access.ensure_at_least(secure::Role::Collaborator)?;
+    let entry = Entry::load(entry_id).await?;
+    EmailVerification::ensure_allowed(entry.contest, &info.username).await?;
+    Ok(json::Json(Score::submit(entry_id, &info.username, &scores.0).await?))
+}
@@ -0,0 +1,32 @@
+use crate::logging::RequestId;
+use crate::model::ItemId;
+use crate::{api, db};
+use rocket::serde::json;
+use crate::model::tokens::{TokenFamily, Session};
+use crate::routes::auth::AccessToken;
+use rocket::http::Status;
+
+#[rocket::get("/sessions")]
+#[instrument(level = "info")]
+pub async fn my_sessions(id: RequestId, token: AccessToken) -> api::Result<json::Json<Vec<Session>>> {
+    Ok(json::Json(TokenFamily::sessions(&token.info.username, token.family).await?))
+}
+
+/// Ends one of the caller's sessions. Ending the current one is the same as logging out.
+#[rocket::delete("/sessions/<session_id>")]
+#[instrument(level = "info")]
+pub async fn revoke_session(id: RequestId, session_id: ItemId, token: AccessToken) -> api::Result<Status> {
+    TokenFamily::revoke_session(&token.info.username, session_id).await?;
+    Ok(Status::Ok)
+}
+
+/// Ends every session but the one making the request.
+#[rocket::delete("/sessions")]
+#[instrument(level = "info")]
+pub async fn revoke_other_sessions(id: RequestId, token: AccessToken) -> api::Result<Status> {
+    let mut tx = db::pool().begin().await?;
+    let revoked = TokenFamily::revoke_others(&token.info.username, token.family, &mut tx).await?;
+    tx.commit().await?;
+    info!(revoked, "revoked other sessions");
+    Ok(Status::Ok)
+}
\ No newline at end of file
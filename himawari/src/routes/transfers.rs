@@ -0,0 +1,59 @@
+use crate::logging::RequestId;
+use crate::model::{users, ItemId};
+use crate::{api, secure};
+use rocket::serde::json;
+use crate::model::contests::Contest;
+use crate::model::transfers::{Transfer, TransferProposal};
+use rocket::response::status;
+use rocket::http::Status;
+
+#[rocket::get("/contest/<contest_id>/transfers")]
+#[instrument(level = "info")]
+pub async fn get_transfers(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Vec<Transfer>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Ok(json::Json(Transfer::for_contest(contest_id).await?))
+}
+
+#[rocket::post("/contest/<contest_id>/transfer", format = "json", data = "<proposal>")]
+#[instrument(level = "info", skip(proposal))]
+pub async fn propose_transfer(id: RequestId, contest_id: ItemId, info: users::Info, proposal: json::Json<TransferProposal>) -> api::Result<status::Custom<json::Json<Transfer>>> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    let contest = Contest::load(contest_id).await?;
+    let res = Transfer::propose(&contest, &proposal.0.new_owner).await?;
+
+    Ok(status::Custom(Status::Created, json::Json(res)))
+}
+
+#[rocket::delete("/contest/<contest_id>/transfer")]
+#[instrument(level = "info")]
+pub async fn cancel_transfer(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<Status> {
+    let access = info.access_level::<Contest>(&contest_id).await?;
+    access.ensure_at_least(secure::Role::Owner)?;
+    Transfer::cancel(contest_id).await?;
+
+    Ok(Status::Ok)
+}
+
+/// The recipient of a transfer usually has no access to the contest yet, so these only check
+/// that the proposal was made to them.
+#[rocket::get("/transfers")]
+#[instrument(level = "info")]
+pub async fn incoming_transfers(id: RequestId, info: users::Info) -> api::Result<json::Json<Vec<Transfer>>> {
+    Ok(json::Json(Transfer::incoming(&info.username).await?))
+}
+
+#[rocket::post("/contest/<contest_id>/transfer/accept")]
+#[instrument(level = "info")]
+pub async fn accept_transfer(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<json::Json<Contest>> {
+    Ok(json::Json(Transfer::accept(contest_id, &info.username).await?))
+}
+
+#[rocket::post("/contest/<contest_id>/transfer/decline")]
+#[instrument(level = "info")]
+pub async fn decline_transfer(id: RequestId, contest_id: ItemId, info: users::Info) -> api::Result<Status> {
+    Transfer::decline(contest_id, &info.username).await?;
+
+    Ok(Status::Ok)
+}
@@ -0,0 +1,28 @@
+use crate::logging::RequestId;
+use crate::model::users::{self, User};
+use crate::api;
+use rocket::serde::json;
+use crate::model::verification::EmailVerification;
+use rocket::http::Status;
+
+#[derive(Deserialize)]
+pub struct VerificationRequest {
+    token: String,
+}
+
+/// Doesn't need a login, since the link is often opened somewhere the user isn't logged in.
+/// Access tokens issued before this still say the address is unverified until they're refreshed.
+#[rocket::post("/email/verify", format = "json", data = "<request>")]
+#[instrument(level = "info", skip(request))]
+pub async fn verify_email(id: RequestId, request: json::Json<VerificationRequest>) -> api::Result<Status> {
+    EmailVerification::confirm(&request.0.token).await?;
+    Ok(Status::Ok)
+}
+
+#[rocket::post("/email/verify/resend")]
+#[instrument(level = "info")]
+pub async fn resend_verification(id: RequestId, info: users::Info) -> api::Result<Status> {
+    let user = User::load_full(&info.username).await?;
+    EmailVerification::send(&user).await?;
+    Ok(Status::Ok)
+}
\ No newline at end of file
@@ -0,0 +1,121 @@
+//! Background jobs: moving contests out of the submissions and judging phases when their
+//! deadlines pass, purging contests that have been in the trash too long, and forgetting token
+//! families and password resets nobody can use any more.
+//!
+//! Every server instance runs the scheduler. Each sweep happens in one transaction that first
+//! takes a transaction-scoped advisory lock, so only one instance sweeps at a time, and the due
+//! contests are locked as they're changed, so a deadline only ever causes one transition.
+
+use crate::model::ItemId;
+use crate::model::contests::{Contest, Phase};
+use crate::model::tokens::TokenFamily;
+use crate::model::resets::PasswordReset;
+use crate::{db, api};
+use sqlx::Acquire;
+use std::time::Duration;
+
+/// How often to look for deadlines that have passed.
+const INTERVAL: Duration = Duration::from_secs(30);
+/// How often to purge expired contests from the trash, token families and password resets.
+/// Purging is idempotent, so instances don't need to coordinate.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Advisory lock key held for the duration of a sweep. Arbitrary, but must not be reused.
+const SWEEP_LOCK: i64 = 0x6869_6d61_0001;
+
+pub async fn run() {
+    let mut interval = tokio::time::interval(INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = sweep().await {
+            error!(error = ?e, "phase scheduler sweep failed");
+        }
+    }
+}
+
+pub async fn purge_trash() {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match Contest::purge_expired().await {
+            Ok(0) => {}
+            Ok(purged) => info!(purged, "purged contests from trash"),
+            Err(e) => error!(error = ?e, "purging trash failed"),
+        }
+    }
+}
+
+pub async fn purge_tokens() {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        match TokenFamily::purge_expired().await {
+            Ok(0) => {}
+            Ok(purged) => info!(purged, "purged expired token families"),
+            Err(e) => error!(error = ?e, "purging token families failed"),
+        }
+        match PasswordReset::purge_expired().await {
+            Ok(0) => {}
+            Ok(purged) => info!(purged, "purged expired password resets"),
+            Err(e) => error!(error = ?e, "purging password resets failed"),
+        }
+    }
+}
+
+/// Performs every transition that is due. Returns without doing anything if another instance is
+/// already sweeping.
+async fn sweep() -> api::Result<()> {
+    let mut tx = db::pool().begin().await?;
+
+    let locked = sqlx::query_scalar!(
+        r#"
+        SELECT pg_try_advisory_xact_lock($1) as "locked!";
+        "#,
+        SWEEP_LOCK
+    ).fetch_one(&mut tx)
+        .await?;
+    if !locked {
+        trace!("another instance is sweeping phase deadlines");
+        return Ok(());
+    }
+
+    let due = sqlx::query!(
+        r#"
+        SELECT id as "id: ItemId", phase as "phase: Phase" FROM contests
+        WHERE deleted IS NULL
+          AND ((phase = 'submissions' AND submission_deadline <= now())
+               OR (phase = 'judging' AND judging_deadline <= now()))
+        ORDER BY id
+        FOR UPDATE SKIP LOCKED;
+        "#
+    ).fetch_all(&mut tx)
+        .await?;
+
+    let mut changed = Vec::with_capacity(due.len());
+    for contest in due {
+        let to = match contest.phase {
+            Phase::Submissions => Phase::Judging,
+            _ => Phase::Deliberation,
+        };
+
+        // Each change gets a savepoint, so one contest that can't change doesn't hold up the rest.
+        let mut savepoint = (&mut tx).begin().await?;
+        match Contest::change_phase_in(contest.id, to, None, &mut savepoint).await {
+            Ok(_) => {
+                savepoint.commit().await?;
+                changed.push((contest.id, contest.phase, to));
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                error!(contest = %contest.id, from = %contest.phase, %to, error = ?e, "couldn't change phase at deadline");
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    for (contest, from, to) in changed {
+        info!(%contest, %from, %to, "deadline passed; contest changed phase");
+    }
+    Ok(())
+}
@@ -3,6 +3,15 @@ use crate::api;
 use crate::model::users;
 use rocket::http::Status;
 use std::borrow::Cow;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use once_cell::sync::Lazy;
+use jwt_simple::prelude::HS256Key;
+
+/// Signs access tokens and the links in verification emails.
+pub static JWT_KEY: Lazy<HS256Key> = Lazy::new(|| {
+    HS256Key::from_bytes(api::need_env_var("JWT_KEY").as_ref())
+});
 
 #[derive(Debug, Clone, PartialEq, thiserror::Error)]
 pub enum Error {
@@ -14,6 +23,8 @@ pub enum Error {
 #[sqlx(type_name = "access_role", rename_all = "lowercase")]
 pub enum Role {
     None,
+    /// Can read, but not judge. Anyone gets this for public and unlisted contests.
+    Viewer,
     Collaborator,
     Owner,
     Moderator,
@@ -23,10 +34,15 @@ pub enum Role {
 serde_plain::forward_display_to_serde!(Role);
 
 #[async_trait::async_trait]
-pub trait GuardedResource {
-    type ResourceId;
+pub trait GuardedResource: Send {
+    type ResourceId: Sync;
 
     async fn access_level(user: &users::Info, rid: &Self::ResourceId) -> api::Result<Role>;
+
+    /// The access someone who isn't logged in has. Nothing, unless the resource says otherwise.
+    async fn anonymous_access_level(_rid: &Self::ResourceId) -> api::Result<Role> {
+        Ok(Role::None)
+    }
 }
 
 impl Role {
@@ -47,4 +63,35 @@ impl api::ResponseError for Error {
     fn message(&self) -> Cow<'static, str> {
         self.to_string().into()
     }
+}
+
+/// A random secret handed to a user in a link or an email. Only its [hash](hash_token) is stored,
+/// so a leaked database doesn't leak usable tokens.
+pub struct SecretToken(String);
+
+impl SecretToken {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        Self(hex::encode(bytes))
+    }
+
+    pub fn hash(&self) -> Vec<u8> {
+        hash_token(&self.0)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<token>")
+    }
+}
+
+/// Tokens are long and random, so a fast unsalted hash is enough.
+pub fn hash_token(token: &str) -> Vec<u8> {
+    Sha256::digest(token.as_bytes()).to_vec()
 }
\ No newline at end of file
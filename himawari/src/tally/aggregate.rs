@@ -0,0 +1,150 @@
+//! Turns per-judge rubric scores into a single ranking.
+
+use crate::model::ItemId;
+use crate::model::users::Username;
+use crate::model::scores::{Criterion, Score};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "aggregation_method", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    Mean,
+    Median,
+    /// Mean after dropping the configured fraction of scores from each end.
+    TrimmedMean,
+    /// Each judge's totals are standardized against that judge's own mean and spread before
+    /// averaging, so harsh and lenient judges count equally.
+    ZScore,
+}
+
+serde_plain::forward_display_to_serde!(Method);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EntryResult {
+    #[serde(rename = "entryId")]
+    pub entry: ItemId,
+    /// `None` if no judge has scored the entry yet.
+    pub score: Option<f64>,
+    pub rank: Option<usize>,
+    pub judges: usize,
+}
+
+/// Collapses each judge's per-criterion scores for an entry into one number between 0 and 1,
+/// weighting criteria by the rubric. Scores for criteria no longer in the rubric are ignored.
+pub fn judge_totals(rubric: &[Criterion], scores: &[Score]) -> BTreeMap<(Username, ItemId), f64> {
+    let criteria: BTreeMap<ItemId, &Criterion> = rubric.iter().map(|c| (c.id, c)).collect();
+
+    // (weighted sum, total weight, unweighted sum, count)
+    let mut sums: BTreeMap<(Username, ItemId), (f64, f64, f64, usize)> = BTreeMap::new();
+    for score in scores {
+        let criterion = match criteria.get(&score.criterion) {
+            Some(c) => c,
+            None => continue,
+        };
+        let normalized = (score.score - criterion.min_score) / (criterion.max_score - criterion.min_score);
+        let acc = sums.entry((score.judge.clone(), score.entry)).or_default();
+        acc.0 += normalized * criterion.weight;
+        acc.1 += criterion.weight;
+        acc.2 += normalized;
+        acc.3 += 1;
+    }
+
+    sums.into_iter()
+        .map(|(k, (weighted, weight, plain, count))| {
+            let total = if weight > 0.0 { weighted / weight } else { plain / count as f64 };
+            (k, total)
+        })
+        .collect()
+}
+
+/// Combines judge totals into a ranked result for every entry in `entries`. Totals from judges
+/// outside `judges` or for entries outside `entries` are ignored. Unscored entries come last,
+/// unranked, in id order.
+pub fn aggregate(
+    method: Method,
+    trim_fraction: f64,
+    totals: &BTreeMap<(Username, ItemId), f64>,
+    judges: &BTreeSet<Username>,
+    entries: &BTreeSet<ItemId>,
+) -> Vec<EntryResult> {
+    let totals: BTreeMap<&(Username, ItemId), f64> = totals.iter()
+        .filter(|((judge, entry), _)| judges.contains(judge) && entries.contains(entry))
+        .map(|(k, v)| (k, *v))
+        .collect();
+
+    let totals = if method == Method::ZScore { standardize(totals) } else { totals };
+
+    let mut per_entry: BTreeMap<ItemId, Vec<f64>> = BTreeMap::new();
+    for ((_, entry), total) in totals {
+        per_entry.entry(*entry).or_default().push(total);
+    }
+
+    let scored: Vec<(ItemId, f64)> = per_entry.iter()
+        .map(|(entry, values)| {
+            let mut values = values.clone();
+            values.sort_by(f64::total_cmp);
+            let score = match method {
+                Method::Mean | Method::ZScore => mean(&values),
+                Method::Median => median(&values),
+                Method::TrimmedMean => {
+                    let cut = (values.len() as f64 * trim_fraction).floor() as usize;
+                    mean(&values[cut..values.len() - cut])
+                }
+            };
+            (*entry, score)
+        })
+        .collect();
+
+    let mut out: Vec<EntryResult> = super::rank(scored).into_iter()
+        .map(|r| EntryResult {
+            entry: r.entry,
+            score: Some(r.score),
+            rank: Some(r.rank),
+            judges: per_entry[&r.entry].len(),
+        })
+        .collect();
+
+    out.extend(entries.iter()
+        .filter(|e| !per_entry.contains_key(*e))
+        .map(|&entry| EntryResult { entry, score: None, rank: None, judges: 0 }));
+    out
+}
+
+fn standardize(totals: BTreeMap<&(Username, ItemId), f64>) -> BTreeMap<&(Username, ItemId), f64> {
+    let mut per_judge: BTreeMap<&Username, Vec<f64>> = BTreeMap::new();
+    for ((judge, _), total) in &totals {
+        per_judge.entry(judge).or_default().push(*total);
+    }
+
+    let stats: BTreeMap<&Username, (f64, f64)> = per_judge.into_iter()
+        .map(|(judge, values)| {
+            let m = mean(&values);
+            let variance = values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / values.len() as f64;
+            (judge, (m, variance.sqrt()))
+        })
+        .collect();
+
+    totals.into_iter()
+        .map(|(k, total)| {
+            let (m, sd) = stats[&k.0];
+            // A judge who gave everything the same score expresses no preference.
+            let z = if sd > 0.0 { (total - m) / sd } else { 0.0 };
+            (k, z)
+        })
+        .collect()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// `values` must be sorted.
+fn median(values: &[f64]) -> f64 {
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
@@ -0,0 +1,40 @@
+//! Ranking and tallying algorithms. Nothing in here touches the database; the `model` layer
+//! loads the raw judgments and hands them over.
+
+use crate::model::ItemId;
+
+pub mod aggregate;
+pub mod ranked;
+pub mod pairwise;
+
+/// Scores are compared at this resolution, so floating point noise from summing in a different
+/// order can't break a tie.
+const TIE_RESOLUTION: f64 = 1e9;
+
+fn tie_key(score: f64) -> i64 {
+    (score * TIE_RESOLUTION).round() as i64
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Ranked {
+    #[serde(rename = "entryId")]
+    pub entry: ItemId,
+    pub score: f64,
+    pub rank: usize,
+}
+
+/// Orders entries by descending score. Tied entries share the better rank ("1224" ranking)
+/// and are listed by ascending id, so the output never depends on input order.
+pub fn rank(mut scored: Vec<(ItemId, f64)>) -> Vec<Ranked> {
+    scored.sort_by_key(|&(id, score)| (std::cmp::Reverse(tie_key(score)), id));
+
+    let mut out: Vec<Ranked> = Vec::with_capacity(scored.len());
+    for (i, (entry, score)) in scored.into_iter().enumerate() {
+        let rank = match out.last() {
+            Some(prev) if tie_key(prev.score) == tie_key(score) => prev.rank,
+            _ => i + 1,
+        };
+        out.push(Ranked { entry, score, rank });
+    }
+    out
+}
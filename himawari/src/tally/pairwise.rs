@@ -0,0 +1,218 @@
+//! Ratings for contests judged by head-to-head comparisons, and the choice of which pair a judge
+//! should compare next.
+//!
+//! Internally every rating is a log-strength `θ`, where entry `i` beats entry `j` with
+//! probability `1 / (1 + e^(θj - θi))`. Elo ratings are the same scale stretched by
+//! [`ELO_SCALE`].
+
+use crate::model::ItemId;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use std::collections::{BTreeMap, BTreeSet};
+use super::Ranked;
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "pairwise_method", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    BradleyTerry,
+    Elo,
+}
+
+serde_plain::forward_display_to_serde!(Method);
+
+/// Every entry is treated as having beaten and lost to an average entry once, which keeps
+/// undefeated and winless entries at finite ratings.
+const PRIOR_GAMES: f64 = 2.0;
+const MAX_ITERATIONS: usize = 1000;
+const CONVERGENCE: f64 = 1e-9;
+const Z_95: f64 = 1.96;
+
+const ELO_BASE: f64 = 1500.0;
+const ELO_K: f64 = 32.0;
+/// Elo points per unit of log-strength.
+const ELO_SCALE: f64 = 400.0 / std::f64::consts::LN_10;
+
+/// How many of the most informative pairs [`next_pair`] picks from at random, so judges working
+/// at the same time aren't all handed the same pair.
+const NEXT_PAIR_POOL: usize = 5;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Rating {
+    #[serde(rename = "entryId")]
+    pub entry: ItemId,
+    pub rating: f64,
+    /// Approximate 95% confidence interval for `rating`.
+    pub lower: f64,
+    pub upper: f64,
+    pub comparisons: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fit {
+    pub method: Method,
+    pub ranking: Vec<Ranked>,
+    pub ratings: Vec<Rating>,
+}
+
+/// A set of `(winner, loser)` results restricted to a fixed list of entries.
+struct Games {
+    order: Vec<ItemId>,
+    /// Indices into `order`, in the order the comparisons were made.
+    results: Vec<(usize, usize)>,
+}
+
+impl Games {
+    fn new(entries: &BTreeSet<ItemId>, results: &[(ItemId, ItemId)]) -> Self {
+        let order: Vec<ItemId> = entries.iter().copied().collect();
+        let index: BTreeMap<ItemId, usize> = order.iter().enumerate().map(|(i, e)| (*e, i)).collect();
+        let results = results.iter()
+            .filter_map(|(w, l)| Some((*index.get(w)?, *index.get(l)?)))
+            .filter(|(w, l)| w != l)
+            .collect();
+        Self { order, results }
+    }
+
+    fn played(&self) -> Vec<usize> {
+        let mut played = vec![0; self.order.len()];
+        for &(w, l) in &self.results {
+            played[w] += 1;
+            played[l] += 1;
+        }
+        played
+    }
+
+    /// Fits Bradley–Terry strengths with the MM algorithm (Hunter, 2004).
+    fn bradley_terry(&self) -> Vec<f64> {
+        let n = self.order.len();
+        let mut wins = vec![0.0; n];
+        let mut opponents: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); n];
+        for &(w, l) in &self.results {
+            wins[w] += 1.0;
+            *opponents[w].entry(l).or_default() += 1.0;
+            *opponents[l].entry(w).or_default() += 1.0;
+        }
+
+        // The prior's average opponent has strength 1, which pins the scale.
+        let mut p = vec![1.0; n];
+        for _ in 0..MAX_ITERATIONS {
+            let next: Vec<f64> = (0..n)
+                .map(|i| {
+                    let denominator = PRIOR_GAMES / (p[i] + 1.0)
+                        + opponents[i].iter().map(|(&j, &games)| games / (p[i] + p[j])).sum::<f64>();
+                    (wins[i] + PRIOR_GAMES / 2.0) / denominator
+                })
+                .collect();
+
+            let change = next.iter().zip(&p).map(|(a, b)| (a.ln() - b.ln()).abs()).fold(0.0, f64::max);
+            p = next;
+            if change < CONVERGENCE {
+                break;
+            }
+        }
+
+        p.into_iter().map(f64::ln).collect()
+    }
+
+    /// Replays the comparisons in order with the standard Elo update.
+    fn elo(&self) -> Vec<f64> {
+        let mut r = vec![ELO_BASE; self.order.len()];
+        for &(w, l) in &self.results {
+            let expected = win_probability((r[w] - ELO_BASE) / ELO_SCALE, (r[l] - ELO_BASE) / ELO_SCALE);
+            r[w] += ELO_K * (1.0 - expected);
+            r[l] -= ELO_K * (1.0 - expected);
+        }
+        r.into_iter().map(|r| (r - ELO_BASE) / ELO_SCALE).collect()
+    }
+
+    /// Standard error of each log-strength from the diagonal of the Fisher information.
+    fn standard_errors(&self, theta: &[f64]) -> Vec<f64> {
+        let mut info: Vec<f64> = theta.iter().map(|&t| PRIOR_GAMES * variance(t, 0.0)).collect();
+        for &(w, l) in &self.results {
+            let v = variance(theta[w], theta[l]);
+            info[w] += v;
+            info[l] += v;
+        }
+        info.into_iter().map(|i| 1.0 / i.sqrt()).collect()
+    }
+}
+
+fn win_probability(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + (b - a).exp())
+}
+
+fn variance(a: f64, b: f64) -> f64 {
+    let p = win_probability(a, b);
+    p * (1.0 - p)
+}
+
+/// Rates every entry in `entries` from `(winner, loser)` results, oldest first. Results involving
+/// other entries are ignored.
+pub fn fit(method: Method, entries: &BTreeSet<ItemId>, results: &[(ItemId, ItemId)]) -> Fit {
+    let games = Games::new(entries, results);
+    let theta = match method {
+        Method::BradleyTerry => games.bradley_terry(),
+        Method::Elo => games.elo(),
+    };
+    let errors = games.standard_errors(&theta);
+    let played = games.played();
+
+    let scale = |t: f64| match method {
+        Method::BradleyTerry => t,
+        Method::Elo => ELO_BASE + ELO_SCALE * t,
+    };
+
+    let ratings: Vec<Rating> = games.order.iter()
+        .enumerate()
+        .map(|(i, &entry)| Rating {
+            entry,
+            rating: scale(theta[i]),
+            lower: scale(theta[i] - Z_95 * errors[i]),
+            upper: scale(theta[i] + Z_95 * errors[i]),
+            comparisons: played[i],
+        })
+        .collect();
+
+    Fit {
+        method,
+        ranking: super::rank(ratings.iter().map(|r| (r.entry, r.rating)).collect()),
+        ratings,
+    }
+}
+
+/// Picks the next pair for a judge to compare: one whose outcome is least certain under the
+/// current Bradley–Terry fit, weighted towards entries with few comparisons. Pairs in `seen` are
+/// skipped. Returns `None` once the judge has compared every pair.
+pub fn next_pair<R: Rng>(
+    entries: &BTreeSet<ItemId>,
+    results: &[(ItemId, ItemId)],
+    seen: &BTreeSet<(ItemId, ItemId)>,
+    rng: &mut R,
+) -> Option<(ItemId, ItemId)> {
+    let games = Games::new(entries, results);
+    let theta = games.bradley_terry();
+    let played = games.played();
+    let n = games.order.len();
+
+    let mut pairs: Vec<(f64, usize, usize)> = Vec::new();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let (a, b) = (games.order[i], games.order[j]);
+            if seen.contains(&(a, b)) || seen.contains(&(b, a)) {
+                continue;
+            }
+            let novelty = 1.0 / (1.0 + played[i] as f64).sqrt() + 1.0 / (1.0 + played[j] as f64).sqrt();
+            pairs.push((variance(theta[i], theta[j]) * novelty, i, j));
+        }
+    }
+
+    pairs.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+    pairs.truncate(NEXT_PAIR_POOL);
+    let &(_, i, j) = pairs.choose(rng)?;
+
+    if rng.gen() {
+        Some((games.order[i], games.order[j]))
+    } else {
+        Some((games.order[j], games.order[i]))
+    }
+}
@@ -0,0 +1,194 @@
+//! Tallies for contests where judges rank entries instead of scoring them.
+//!
+//! Ballots may be partial: every ranked entry is preferred over every unranked one, and unranked
+//! entries are tied with each other.
+
+use crate::model::ItemId;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use super::Ranked;
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "ballot_method", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    Borda,
+    InstantRunoff,
+    Schulze,
+}
+
+serde_plain::forward_display_to_serde!(Method);
+
+/// One round of an instant-runoff count.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round {
+    /// First preferences for each entry still in the running.
+    pub votes: BTreeMap<ItemId, usize>,
+    /// Ballots that rank none of the remaining entries.
+    pub exhausted: usize,
+    pub eliminated: Option<ItemId>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tally {
+    pub method: Method,
+    pub ranking: Vec<Ranked>,
+    /// Row and column order of the matrices below.
+    pub candidates: Vec<ItemId>,
+    /// `pairwise[i][j]` is the number of ballots preferring `candidates[i]` to `candidates[j]`.
+    pub pairwise: Vec<Vec<usize>>,
+    #[serde(rename = "strongestPaths", skip_serializing_if = "Option::is_none")]
+    pub strongest_paths: Option<Vec<Vec<usize>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rounds: Option<Vec<Round>>,
+}
+
+/// Tallies `ballots` over `candidates`. Ballot entries that aren't candidates are dropped, as are
+/// repeats of an entry already ranked higher on the same ballot.
+pub fn tally(method: Method, candidates: &BTreeSet<ItemId>, ballots: &[Vec<ItemId>]) -> Tally {
+    let ballots: Vec<Vec<ItemId>> = ballots.iter()
+        .map(|b| {
+            let mut seen = BTreeSet::new();
+            b.iter().copied().filter(|e| candidates.contains(e) && seen.insert(*e)).collect()
+        })
+        .collect();
+
+    let order: Vec<ItemId> = candidates.iter().copied().collect();
+    let pairwise = pairwise(&order, &ballots);
+    let borda = borda(&order, &ballots);
+
+    let mut out = Tally {
+        method,
+        ranking: Vec::new(),
+        candidates: order.clone(),
+        pairwise,
+        strongest_paths: None,
+        rounds: None,
+    };
+
+    match method {
+        Method::Borda => {
+            out.ranking = super::rank(borda.iter().map(|(e, p)| (*e, *p as f64)).collect());
+        }
+        Method::InstantRunoff => {
+            let (ranking, rounds) = instant_runoff(candidates, &ballots, &borda);
+            out.ranking = ranking;
+            out.rounds = Some(rounds);
+        }
+        Method::Schulze => {
+            let paths = strongest_paths(&out.pairwise);
+            let n = order.len();
+            let wins = (0..n)
+                .map(|i| (order[i], (0..n).filter(|&j| paths[i][j] > paths[j][i]).count() as f64))
+                .collect();
+            out.ranking = super::rank(wins);
+            out.strongest_paths = Some(paths);
+        }
+    }
+
+    out
+}
+
+fn pairwise(order: &[ItemId], ballots: &[Vec<ItemId>]) -> Vec<Vec<usize>> {
+    let n = order.len();
+    let index: BTreeMap<ItemId, usize> = order.iter().enumerate().map(|(i, e)| (*e, i)).collect();
+    let mut d = vec![vec![0; n]; n];
+
+    for ballot in ballots {
+        let mut unranked: BTreeSet<usize> = (0..n).collect();
+        for entry in ballot {
+            let i = index[entry];
+            unranked.remove(&i);
+            // Everything not yet seen on this ballot is ranked lower.
+            for &j in &unranked {
+                d[i][j] += 1;
+            }
+        }
+    }
+
+    d
+}
+
+/// Each ballot awards `n - 1` points to its first choice, `n - 2` to its second, and so on.
+fn borda(order: &[ItemId], ballots: &[Vec<ItemId>]) -> BTreeMap<ItemId, usize> {
+    let n = order.len();
+    let mut points: BTreeMap<ItemId, usize> = order.iter().map(|e| (*e, 0)).collect();
+    for ballot in ballots {
+        for (k, entry) in ballot.iter().enumerate() {
+            *points.get_mut(entry).unwrap() += n - 1 - k;
+        }
+    }
+    points
+}
+
+/// Eliminates one entry per round until a single entry remains; the final ranking is the reverse
+/// of the elimination order. Ties for last place are broken by Borda points, then by eliminating
+/// the higher entry id.
+fn instant_runoff(candidates: &BTreeSet<ItemId>, ballots: &[Vec<ItemId>], borda: &BTreeMap<ItemId, usize>) -> (Vec<Ranked>, Vec<Round>) {
+    let mut remaining = candidates.clone();
+    let mut rounds = Vec::new();
+
+    while !remaining.is_empty() {
+        let mut votes: BTreeMap<ItemId, usize> = remaining.iter().map(|e| (*e, 0)).collect();
+        let mut exhausted = 0;
+        for ballot in ballots {
+            match ballot.iter().find(|e| remaining.contains(*e)) {
+                Some(e) => *votes.get_mut(e).unwrap() += 1,
+                None => exhausted += 1,
+            }
+        }
+
+        let eliminated = if remaining.len() > 1 {
+            votes.iter()
+                .min_by_key(|(e, v)| (**v, borda[*e], Reverse(**e)))
+                .map(|(e, _)| *e)
+        } else {
+            None
+        };
+
+        rounds.push(Round { votes, exhausted, eliminated });
+        match eliminated {
+            Some(e) => { remaining.remove(&e); }
+            None => break,
+        }
+    }
+
+    let total = rounds.len();
+    let mut survived: Vec<(ItemId, f64)> = rounds.iter()
+        .enumerate()
+        .filter_map(|(r, round)| round.eliminated.map(|e| (e, (r + 1) as f64)))
+        .collect();
+    if let Some(winner) = rounds.last().and_then(|r| r.votes.keys().next()) {
+        survived.push((*winner, total as f64));
+    }
+
+    (super::rank(survived), rounds)
+}
+
+/// Widest paths through the pairwise defeat graph (the core of the Schulze method).
+fn strongest_paths(d: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = d.len();
+    let mut p = vec![vec![0; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            if i != j && d[i][j] > d[j][i] {
+                p[i][j] = d[i][j];
+            }
+        }
+    }
+
+    for i in 0..n {
+        for j in 0..n {
+            if i == j {
+                continue;
+            }
+            for k in 0..n {
+                if i != k && j != k {
+                    p[j][k] = p[j][k].max(p[j][i].min(p[i][k]));
+                }
+            }
+        }
+    }
+
+    p
+}
@@ -1,11 +1,12 @@
-import React, {useReducer} from "react";
-import {LoggedInUser, UserRef} from "../model/users";
-import {User} from "../../model";
+import React, {useEffect, useReducer} from "react";
+import {LoggedInUser, refreshTokens, tokenExpiry, UserRef} from "../model/users";
+import {Http, User} from "../../model";
 import localforage from "localforage";
 
 export interface AuthInfo {
     info: User.Info,
-    jwt: string
+    jwt: string,
+    refreshToken: string
 }
 
 export type AuthState = AuthInfo | null;
@@ -22,11 +23,12 @@ export interface DefinitelyAuthContextState {
 
 export interface AuthAction {
     readonly type: "login" | "logout";
-    readonly jwt?: string
+    readonly jwt?: string,
+    readonly refreshToken?: string
 }
 
-function infoFromJwt(jwt: string | undefined | null): AuthState {
-    if (!jwt) {
+function infoFromJwt(jwt: string | undefined | null, refreshToken: string | undefined | null): AuthState {
+    if (!jwt || !refreshToken) {
         return null;
     }
 
@@ -38,33 +40,90 @@ function infoFromJwt(jwt: string | undefined | null): AuthState {
     } else {
         return {
             info: loggedIn.value,
-            jwt
+            jwt,
+            refreshToken
         };
     }
 }
 
 const AUTH_KEY = "authTok";
+const REFRESH_KEY = "refreshTok";
+/** How long before an access token expires to swap it for a new one. */
+const REFRESH_MARGIN_MS = 60 * 1000;
+/** Spreads out refreshes so that when several tabs share a login, one goes first and the rest pick up its tokens. */
+const REFRESH_JITTER_MS = 30 * 1000;
+/** How long to wait before trying again when the server couldn't be reached. */
+const RETRY_MS = 30 * 1000;
 
 export const AuthContext: React.Context<AuthContextState> = React.createContext({} as AuthContextState);
 
 export function AuthProvider(props: React.PropsWithChildren<{}>) {
-    let curState = localStorage.getItem(AUTH_KEY);
-
     function reducer(state: AuthState, action: AuthAction) {
         switch (action.type) {
             case "login":
-                let out = infoFromJwt(action.jwt);
+                let out = infoFromJwt(action.jwt, action.refreshToken);
                 if (out) {
                     localStorage.setItem(AUTH_KEY, action.jwt!);
+                    localStorage.setItem(REFRESH_KEY, action.refreshToken!);
                 }
                 return out;
             case "logout":
                 localStorage.removeItem(AUTH_KEY);
+                localStorage.removeItem(REFRESH_KEY);
                 return null;
         }
     }
 
-    const [state, dispatch] = useReducer(reducer, infoFromJwt(curState));
+    const [state, dispatch] = useReducer(reducer, infoFromJwt(localStorage.getItem(AUTH_KEY), localStorage.getItem(REFRESH_KEY)));
+
+    // Another tab logging in, out or refreshing changes the stored tokens, which this one follows.
+    useEffect(() => {
+        function onStorage(event: StorageEvent) {
+            if (event.key !== AUTH_KEY && event.key !== REFRESH_KEY) {
+                return;
+            }
+
+            let jwt = localStorage.getItem(AUTH_KEY);
+            let refreshToken = localStorage.getItem(REFRESH_KEY);
+            if (jwt && refreshToken) {
+                dispatch({type: "login", jwt, refreshToken});
+            } else if (!jwt && !refreshToken) {
+                dispatch({type: "logout"});
+            }
+        }
+
+        window.addEventListener("storage", onStorage);
+        return () => window.removeEventListener("storage", onStorage);
+    }, []);
+
+    // Access tokens only last a few minutes, so swap each one for a new one shortly before it runs out.
+    useEffect(() => {
+        if (!state) {
+            return;
+        }
+
+        let delay = tokenExpiry(state.jwt) - Date.now() - REFRESH_MARGIN_MS - Math.random() * REFRESH_JITTER_MS;
+        let timer = setTimeout(async function refresh() {
+            // Refresh tokens only work once, so if another tab got here first, use what it got.
+            let stored = localStorage.getItem(REFRESH_KEY);
+            if (stored && stored !== state.refreshToken) {
+                dispatch({type: "login", jwt: localStorage.getItem(AUTH_KEY)!, refreshToken: stored});
+                return;
+            }
+
+            let res = await refreshTokens(state.refreshToken).catch(() => null);
+            if (res?.isOk()) {
+                dispatch({type: "login", jwt: res.value.token, refreshToken: res.value.refreshToken});
+            } else if (res?.isErr() && res.error instanceof Http.Unauthorized) {
+                // The refresh token has expired or been revoked, so there's no staying logged in.
+                dispatch({type: "logout"});
+            } else {
+                timer = setTimeout(refresh, RETRY_MS);
+            }
+        }, Math.max(delay, 0));
+
+        return () => clearTimeout(timer);
+    }, [state]);
 
     return <AuthContext.Provider value={{state, dispatch}}>{props.children}</AuthContext.Provider>
 }
\ No newline at end of file
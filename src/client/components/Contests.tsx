@@ -1,4 +1,4 @@
-import {useQuery, useQueryClient} from "react-query";
+import {useInfiniteQuery, useQueryClient} from "react-query";
 import {AuthContext, AuthContextState, AuthState} from "./AuthContext";
 import {Contest} from "../../model/contests";
 import {Http, ValidationFailure} from "../../model";
@@ -25,7 +25,9 @@ import ContestDeletionModal from "./ContestDeletionModal";
 import ContestInfoCard from "./ContestInfoCard";
 
 export function Contests(props: { state: AuthContextState }) {
-    const contests = useQuery<Contest.Info[], Http.Error>(["contests", props.state.state?.jwt], ({queryKey}) => Contest.getForUserOrThrow(queryKey[1] as string));
+    const contests = useInfiniteQuery<Contest.Page<Contest.Info>, Http.Error>(["contests", props.state.state?.jwt],
+        ({queryKey, pageParam}) => Contest.getForUserOrThrow(queryKey[1] as string, pageParam),
+        {getNextPageParam: (last) => last.nextCursor ?? undefined});
     useEffect(() => {
         if (contests.isError) {
             toast.error(contests.error.longMessage());
@@ -36,9 +38,21 @@ export function Contests(props: { state: AuthContextState }) {
         return <Redirect to={"/login"}/>;
     }
 
+    // Pages are only fetched when asked for, so the listings below are the ones loaded so far.
+    let pages = contests.data?.pages || [];
+    let listings = pages.flatMap((page) => page.items);
+    let total = pages.length > 0 ? pages[pages.length - 1].total : 0;
+
     return <div className={"Contests"}>
         {contests.isLoading && <LoadingSpinner/>}
-        <ContestListings state={props.state} listings={contests.data || []}/>
+        <ContestListings state={props.state} listings={listings}/>
+        {contests.hasNextPage &&
+            <Container className={"text-center mt-2"}>
+                <Button variant={"outline-secondary"} onClick={() => contests.fetchNextPage()}
+                        disabled={contests.isFetchingNextPage}>
+                    {contests.isFetchingNextPage ? <LoadingSpinner/> : `Load more contests (${listings.length} of ${total})`}
+                </Button>
+            </Container>}
     </div>
 }
 
@@ -171,7 +171,7 @@ class Login extends React.Component<LoginProps & RouteComponentProps, LoginState
                 toast.error("Login failed. Please check your username and password before trying again.");
             }
         } else {
-            this.props.state.dispatch({type: "login", jwt: out.value.token})
+            this.props.state.dispatch({type: "login", jwt: out.value.token, refreshToken: out.value.refreshToken})
             this.setState({redirectToReferrer: true});
         }
     }
@@ -2,12 +2,22 @@ import {useHistory} from "react-router-dom";
 import {useEffect} from "react";
 import {toast} from "react-toastify";
 import {AuthContextState} from "./AuthContext";
+import {UserRef} from "../model/users";
 
 
 export default function(props: {state: AuthContextState}) {
     let history = useHistory();
 
     useEffect(() => {
+        let auth = props.state.state;
+        if (auth) {
+            // Revokes the refresh token too, so a copy of it can't be used to log back in.
+            new UserRef(auth.info.username).logOut(auth.jwt).then(res => {
+                if (res.isErr()) {
+                    console.error(res.error);
+                }
+            });
+        }
         props.state.dispatch({type: "logout"});
         toast.info("You are now logged out.");
         history.push("/");
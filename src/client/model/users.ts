@@ -12,10 +12,12 @@ export class UserRef implements User.Ref {
         this.username = username;
     }
 
-    async logOut(): Promise<Result<void, Http.AnyError>> {
-        let resp = await fetch("/api/login", {
-            method: "DELETE",
-            credentials: "same-origin"
+    async logOut(tok: string): Promise<Result<void, Http.AnyError>> {
+        let resp = await fetch("/api/logout", {
+            method: "POST",
+            headers: {
+                "Authorization": `Bearer: ${tok}`
+            }
         });
 
         if (resp.ok) {
@@ -26,6 +28,36 @@ export class UserRef implements User.Ref {
     }
 }
 
+/** What logging in or refreshing gives back. `expiresIn` is how many seconds `token` lasts for. */
+export interface Tokens {
+    token: string;
+    refreshToken: string;
+    expiresIn: number;
+}
+
+/** Trades a refresh token for a new access token. Each refresh token only works once. */
+export async function refreshTokens(refreshToken: string): Promise<Result<Tokens, Http.AnyError>> {
+    let resp = await fetch("/api/token/refresh", {
+        method: "POST",
+        body: JSON.stringify({refreshToken}),
+        headers: {
+            "Content-Type": "application/json"
+        }
+    });
+
+    if (resp.ok) {
+        return ok(await resp.json());
+    } else {
+        return err(Http.Error.fromStatus(resp.status));
+    }
+}
+
+/** When `tok` expires, in milliseconds since the epoch, or 0 if it can't be read. */
+export function tokenExpiry(tok: string): number {
+    let parsed: any = jwt.decode(tok, {json: true});
+    return parsed?.exp ? parsed.exp * 1000 : 0;
+}
+
 export class BadLogin extends SimpleMessageError {
     constructor() {
         super("Invalid login. Please check your username and password.");
@@ -40,7 +72,7 @@ export class LoginUser extends UserRef implements User.LoginRequest {
         this.password = password;
     }
 
-    async logIn(): Promise<Result<{token: string}, Http.AnyError | BadLogin>> {
+    async logIn(): Promise<Result<Tokens, Http.AnyError | BadLogin>> {
         let resp = await fetch("/api/login", {
             method: "POST",
             body: JSON.stringify(this),
@@ -15,13 +15,28 @@ export namespace Contest {
         created: Date;
     }
 
-    export async function getForUser(tok: string | undefined): Promise<Result<Info[], Http.Error>> {
+    /** One page of a listing. `nextCursor` is passed back as `cursor` to get the next one. */
+    export interface Page<T> {
+        items: T[];
+        nextCursor: string | null;
+        total: number;
+    }
+
+    /** How many contests to ask for in one page. */
+    const PAGE_SIZE = 20;
+
+    export async function getForUser(tok: string | undefined, cursor?: string | null): Promise<Result<Page<Info>, Http.Error>> {
 
         if (!tok) {
             return err(Http.Error.fromStatus(StatusCodes.UNAUTHORIZED));
         }
 
-        let res = await fetch("/api/contest", {
+        let query = new URLSearchParams({limit: `${PAGE_SIZE}`});
+        if (cursor) {
+            query.set("cursor", cursor);
+        }
+
+        let res = await fetch(`/api/contest?${query}`, {
             method: "GET",
             headers: {
                 "Authorization": `Bearer: ${tok}`
@@ -30,16 +45,18 @@ export namespace Contest {
 
         if (!res.ok) {
             return err(Http.Error.fromStatus(res.status));
-        } else {
-            let json = await res.json();
-            let parsed = json.map((val: {id: number, name: string, owner: string, created: string}) => {
+        }
+
+        let page: Page<{id: number, name: string, owner: string, created: string}> = await res.json();
+        return ok({
+            ...page,
+            items: page.items.map(val => {
                 return {
                     ...val,
                     created: new Date(val.created)
                 }
             })
-            return ok(parsed);
-        }
+        });
     }
 
     export async function deleteContest(tok: string, id: number): Promise<Result<void, Http.Error>> {
@@ -56,8 +73,8 @@ export namespace Contest {
         return ok(undefined);
     }
 
-    export async function getForUserOrThrow(tok: string | undefined): Promise<Info[]> {
-        let res = await getForUser(tok);
+    export async function getForUserOrThrow(tok: string | undefined, cursor?: string | null): Promise<Page<Info>> {
+        let res = await getForUser(tok, cursor);
         if (res.isErr()) {
             throw res.error;
         } else {